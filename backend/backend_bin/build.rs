@@ -1,3 +1,3 @@
 fn main() {
-    schemagen::gen_schema();
+    schemagen::gen_schema().expect("Schema generation should succeed!");
 }
@@ -1,9 +1,22 @@
-use std::{env, fs};
+use std::env;
+use std::time::Duration;
 
 use backend_lib::connection::Connection;
 use backend_lib::state::server_state::ServerState;
+use backend_lib::state::storage::JsonFileStorage;
+use backend_lib::tasks;
 use tokio::net::TcpListener;
 
+const SNAPSHOT_PERIOD: Duration = Duration::from_secs(30);
+const REJOIN_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+const REJOIN_SWEEP_PERIOD: Duration = Duration::from_secs(30);
+const BETTING_DEADLINE_PERIOD: Duration = Duration::from_secs(1);
+//Defaults for ROOM_IDLE_TTL_SECS and ROOM_MAX_AGE_SECS
+const ROOM_IDLE_TTL: Duration = Duration::from_secs(60 * 60);
+//None lets a room live as long as somebody is in it
+const ROOM_MAX_AGE: Option<Duration> = None;
+const ROOM_SWEEP_PERIOD: Duration = Duration::from_secs(60);
+
 #[tokio::main]
 async fn main() {
     env_logger::builder()
@@ -13,20 +26,75 @@ async fn main() {
     let addr = env::args()
         .nth(1)
         .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let snapshot_path = env::args()
+        .nth(2)
+        .unwrap_or_else(|| "rooms.json".to_string());
+    let room_idle_ttl = env_secs("ROOM_IDLE_TTL_SECS").unwrap_or(ROOM_IDLE_TTL);
+    let room_max_age = env_secs("ROOM_MAX_AGE_SECS").or(ROOM_MAX_AGE);
+    log::info!("Rooms expire after {room_idle_ttl:?} idle, max age {room_max_age:?}");
 
     let listener = TcpListener::bind(&addr).await.unwrap();
     log::trace!("Listening on: {}", addr);
-    let state = ServerState::new();
-    while let Ok((stream, new_addr)) = listener.accept().await {
-        let ws_stream = match tokio_tungstenite::accept_async(stream).await {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("Failed to accept connection!: {e}");
-                continue;
-            }
-        };
-        log::trace!("Connection accepted from: {}", new_addr);
-        let connection = Connection::new(new_addr, ws_stream, state.clone());
-        tokio::spawn(connection.handle_connection());
+    let state = ServerState::with_storage(JsonFileStorage::new(&snapshot_path))
+        .expect("Room snapshot should be readable!");
+    tokio::spawn(tasks::snapshot_rooms(state.clone(), SNAPSHOT_PERIOD));
+    tokio::spawn(tasks::expire_disconnected_users(
+        state.clone(),
+        REJOIN_GRACE_PERIOD,
+        REJOIN_SWEEP_PERIOD,
+    ));
+    tokio::spawn(tasks::close_due_betting(
+        state.clone(),
+        BETTING_DEADLINE_PERIOD,
+    ));
+    tokio::spawn(tasks::expire_idle_rooms(
+        state.clone(),
+        room_idle_ttl,
+        room_max_age,
+        ROOM_SWEEP_PERIOD,
+    ));
+    let accept_connections = async {
+        while let Ok((stream, new_addr)) = listener.accept().await {
+            let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+                Ok(s) => s,
+                Err(e) => {
+                    log::error!("Failed to accept connection!: {e}");
+                    continue;
+                }
+            };
+            log::trace!("Connection accepted from: {}", new_addr);
+            let connection = Connection::new(new_addr, ws_stream, state.clone());
+            tokio::spawn(connection.handle_connection());
+        }
+    };
+    tokio::select! {
+        _ = accept_connections => {},
+        _ = shutdown_signal() => log::info!("Shutting down"),
+    }
+    if let Err(e) = state.read().save_rooms() {
+        log::error!("Failed to snapshot rooms on shutdown: {e}");
+    }
+}
+
+fn env_secs(name: &str) -> Option<Duration> {
+    let secs = env::var(name).ok()?;
+    let secs = secs
+        .parse()
+        .unwrap_or_else(|_| panic!("{name} should be a whole number of seconds!"));
+    Some(Duration::from_secs(secs))
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate =
+            signal(SignalKind::terminate()).expect("Should be able to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
     }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -86,8 +86,8 @@ impl From<futures::channel::mpsc::TrySendError<WSMessage>> for MessageSendError
         Self::TrySendError(v)
     }
 }
-impl Into<ServerMessage> for MessageSendError {
-    fn into(self) -> ServerMessage {
+impl From<MessageSendError> for ServerMessage {
+    fn from(_val: MessageSendError) -> Self {
         ServerMessage::Error {
             description:
                 "Internal Server Error, this is likely a bug, please contact the maintainer.".into(),
@@ -1,4 +1,4 @@
-use std::net::SocketAddr;
+use std::{collections::HashMap, net::SocketAddr};
 
 use serde::{Deserialize, Serialize};
 
@@ -6,9 +6,12 @@ use super::{WSMessage, error::MessageParseError};
 use crate::state::{
     ID,
     room::{
-        MemberState, RoomCode,
-        pot::Pot,
-        wager::{Wager, WagerOutcome},
+        BettingStatus, MemberState, RoomCode,
+        config::{RoomConfig, RoomConfigPatch},
+        ledger::LedgerEntry,
+        pot::{Pot, PotSplit},
+        role::{Permission, Role},
+        wager::{Wager, WagerKind, WagerOutcome},
     },
 };
 
@@ -23,56 +26,155 @@ pub enum Destination {
 }
 
 //What would normally be unit variants are struct variants to make dealing
-//with the frontend types easier
+//with the frontend types easier. Everything about a room says which one,
+//since a session can be in several at once
 #[derive(Serialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
 #[serde(tag = "kind")]
 pub enum ServerMessage {
     SynchronizeRoom {
+        room_code: RoomCode,
         members: Vec<MemberState>,
         pots: Vec<Pot>,
         wager: Vec<Wager>,
-        requester_id: ID,
+        //None for watchers, who aren't members
+        requester_id: Option<ID>,
+        //Likewise None for watchers, who have no membership to resume
+        member_token: Option<String>,
+        config: RoomConfig,
     },
     RoomCreated {
         code: RoomCode,
     },
 
     UserJoined {
+        room_code: RoomCode,
         name: String,
         id: ID,
     },
-    RoomDeleted,
+    RoomDeleted {
+        room_code: RoomCode,
+    },
     UserRemoved {
+        room_code: RoomCode,
         id: ID,
     },
     PotCreated {
+        room_code: RoomCode,
         pot: Pot,
     },
     PotJoined {
+        room_code: RoomCode,
         pot_id: ID,
         user_id: ID,
+        amount: i64,
     },
     PotResolved {
+        room_code: RoomCode,
         id: ID,
     },
+    PotCancelled {
+        room_code: RoomCode,
+        id: ID,
+    },
+    PotDrawn {
+        room_code: RoomCode,
+        pot_id: ID,
+        seed: u64,
+        weighted: bool,
+        winner: ID,
+    },
+    PotUpdated {
+        room_code: RoomCode,
+        pot: Pot,
+    },
+    //Taken away by an undo rather than resolved
+    PotRemoved {
+        room_code: RoomCode,
+        pot_id: ID,
+    },
+    PotStatusChanged {
+        room_code: RoomCode,
+        pot_id: ID,
+        status: BettingStatus,
+        closes_at: Option<u64>,
+    },
     WagerCreated {
+        room_code: RoomCode,
         wager: Wager,
     },
     WagerJoined {
+        room_code: RoomCode,
         wager_id: ID,
         user_id: ID,
         outcome_id: ID,
         amount: i64,
     },
+    //delta is how much the bet moved by, negative when it was lowered or withdrawn
+    BetChanged {
+        room_code: RoomCode,
+        wager_id: ID,
+        user_id: ID,
+        outcome_id: ID,
+        delta: i64,
+    },
     WagerResolved {
+        room_code: RoomCode,
+        id: ID,
+    },
+    WagerCancelled {
+        room_code: RoomCode,
         id: ID,
     },
+    WagerUpdated {
+        room_code: RoomCode,
+        wager: Wager,
+    },
+    //Taken away by an undo rather than resolved
+    WagerRemoved {
+        room_code: RoomCode,
+        wager_id: ID,
+    },
+    WagerStatusChanged {
+        room_code: RoomCode,
+        wager_id: ID,
+        status: BettingStatus,
+        closes_at: Option<u64>,
+    },
+    WagerOddsChanged {
+        room_code: RoomCode,
+        wager_id: ID,
+        odds: HashMap<ID, usize>,
+    },
     ScoreChanged {
+        room_code: RoomCode,
         user_id: ID,
         new_amount: i64,
     },
-    AdminGranted,
-    RecieverLeft,
+    ActionsUndone {
+        room_code: RoomCode,
+        count: usize,
+    },
+    RoomConfigChanged {
+        room_code: RoomCode,
+        config: RoomConfig,
+    },
+    History {
+        room_code: RoomCode,
+        entries: Vec<LedgerEntry>,
+        offset: usize,
+        total: usize,
+    },
+    AdminGranted {
+        room_code: RoomCode,
+    },
+    RoleChanged {
+        room_code: RoomCode,
+        id: ID,
+        role: Role,
+    },
+    RecieverLeft {
+        room_code: RoomCode,
+    },
 
     Error {
         description: String,
@@ -93,11 +195,27 @@ pub enum ClientMessage {
     CreateRoom {
         code: RoomCode,
         admin_pass: String,
+        #[serde(default)]
+        config: RoomConfig,
+    },
+    //Like CreateRoom, but the server picks the code and replies with RoomCreated
+    CreateGeneratedRoom {
+        admin_pass: String,
+        #[serde(default)]
+        config: RoomConfig,
     },
     JoinRoom {
         code: RoomCode,
         name: String,
     },
+    Rejoin {
+        code: RoomCode,
+        token: String,
+    },
+    //Follows a room without joining it, LeaveRoom stops watching
+    WatchRoom {
+        code: RoomCode,
+    },
     LeaveRoom {
         room_code: RoomCode,
     },
@@ -113,18 +231,22 @@ pub enum ClientMessage {
         password: String,
     },
     BlessScore {
+        room_code: RoomCode,
         to: ID,
         amount: i64,
     },
     RemoveScore {
+        room_code: RoomCode,
         from: ID,
         amount: i64,
     },
     GiveScore {
+        room_code: RoomCode,
         to: ID,
         amount: i64,
     },
     TransferScore {
+        room_code: RoomCode,
         from: ID,
         to: ID,
         amount: i64,
@@ -133,20 +255,52 @@ pub enum ClientMessage {
         room_code: RoomCode,
         score_requirement: i64,
         description: String,
+        #[serde(default)]
+        closes_at: Option<u64>,
+        #[serde(default)]
+        variable_buy_in: bool,
     },
     JoinPot {
         room_code: RoomCode,
         pot_id: ID,
+        //Only used by variable buy-in pots, defaults to the minimum
+        #[serde(default)]
+        amount: Option<i64>,
+    },
+    LockPot {
+        room_code: RoomCode,
+        pot_id: ID,
+        #[serde(default)]
+        closes_at: Option<u64>,
     },
     ResolvePot {
         room_id: RoomCode,
         pot_id: ID,
-        winner: ID,
+        winners: Vec<ID>,
+        #[serde(default)]
+        split: PotSplit,
+        //For when the house awards a pot to someone who never bought in
+        #[serde(default)]
+        allow_outsiders: bool,
+    },
+    DrawPot {
+        room_code: RoomCode,
+        pot_id: ID,
+        #[serde(default)]
+        weighted: bool,
+    },
+    CancelPot {
+        room_code: RoomCode,
+        pot_id: ID,
     },
     CreateWager {
         room_id: RoomCode,
         name: String,
         outcomes: Vec<WagerOutcome>,
+        #[serde(default)]
+        wager_kind: WagerKind,
+        #[serde(default)]
+        closes_at: Option<u64>,
     },
     JoinWager {
         room_id: RoomCode,
@@ -154,67 +308,328 @@ pub enum ClientMessage {
         outcome_id: ID,
         amount: i64,
     },
+    ChangeBet {
+        room_code: RoomCode,
+        wager_id: ID,
+        outcome_id: ID,
+        amount: i64,
+    },
+    WithdrawBet {
+        room_code: RoomCode,
+        wager_id: ID,
+        outcome_id: ID,
+    },
+    LockWager {
+        room_code: RoomCode,
+        wager_id: ID,
+        #[serde(default)]
+        closes_at: Option<u64>,
+    },
     ResolveWager {
         room_id: RoomCode,
         wager_id: ID,
         outcome_id: ID,
     },
+    CancelWager {
+        room_code: RoomCode,
+        wager_id: ID,
+    },
+    RequestHistory {
+        room_code: RoomCode,
+        offset: usize,
+        limit: usize,
+    },
+    Undo {
+        room_code: RoomCode,
+        count: usize,
+    },
+    UpdateRoomConfig {
+        room_code: RoomCode,
+        changes: RoomConfigPatch,
+    },
+    GrantRole {
+        room_code: RoomCode,
+        id: ID,
+        role: Role,
+    },
+    //Puts the member back to being a player
+    RevokeRole {
+        room_code: RoomCode,
+        id: ID,
+    },
     Debug,
 }
 
 impl ClientMessage {
-    pub fn requires_admin(&self) -> Option<Option<RoomCode>> {
+    pub fn required_permission(&self) -> Option<(RoomCode, Permission)> {
         match self {
             ClientMessage::CreateRoom {
                 code: _,
                 admin_pass: _,
+                config: _,
+            } => None,
+            ClientMessage::CreateGeneratedRoom {
+                admin_pass: _,
+                config: _,
             } => None,
             ClientMessage::JoinRoom { code: _, name: _ } => None,
+            ClientMessage::Rejoin { code: _, token: _ } => None,
+            ClientMessage::WatchRoom { code: _ } => None,
             ClientMessage::LeaveRoom { room_code: _ } => None,
-            ClientMessage::RemoveFromRoom { code, id: _ } => Some(Some(*code)),
-            ClientMessage::DeleteRoom { room_code: code } => Some(Some(*code)),
+            ClientMessage::RemoveFromRoom { code, id: _ } => {
+                Some((*code, Permission::RemoveMembers))
+            }
+            ClientMessage::DeleteRoom { room_code: code } => Some((*code, Permission::ManageRoom)),
             ClientMessage::RequestAdmin {
                 room: _,
                 password: _,
             } => None,
-            ClientMessage::BlessScore { to: _, amount: _ } => Some(None),
-            ClientMessage::RemoveScore { from: _, amount: _ } => Some(None),
-            ClientMessage::GiveScore { to: _, amount: _ } => None,
+            ClientMessage::BlessScore {
+                room_code,
+                to: _,
+                amount: _,
+            } => Some((*room_code, Permission::ManageScores)),
+            ClientMessage::RemoveScore {
+                room_code,
+                from: _,
+                amount: _,
+            } => Some((*room_code, Permission::ManageScores)),
+            ClientMessage::GiveScore {
+                room_code,
+                to: _,
+                amount: _,
+            } => Some((*room_code, Permission::Play)),
             ClientMessage::TransferScore {
+                room_code,
                 from: _,
                 to: _,
                 amount: _,
-            } => None,
+            } => Some((*room_code, Permission::Play)),
             ClientMessage::CreatePot {
                 room_code,
                 score_requirement: _,
                 description: _,
-            } => Some(Some(*room_code)),
+                closes_at: _,
+                variable_buy_in: _,
+            } => Some((*room_code, Permission::ManageStakes)),
             ClientMessage::JoinPot {
-                room_code: _,
+                room_code,
                 pot_id: _,
-            } => None,
+                amount: _,
+            } => Some((*room_code, Permission::Play)),
+            ClientMessage::LockPot {
+                room_code,
+                pot_id: _,
+                closes_at: _,
+            } => Some((*room_code, Permission::ManageStakes)),
             ClientMessage::ResolvePot {
                 room_id,
                 pot_id: _,
-                winner: _,
-            } => Some(Some(*room_id)),
+                winners: _,
+                split: _,
+                allow_outsiders: _,
+            } => Some((*room_id, Permission::ManageStakes)),
+            ClientMessage::DrawPot {
+                room_code,
+                pot_id: _,
+                weighted: _,
+            } => Some((*room_code, Permission::ManageStakes)),
+            ClientMessage::CancelPot {
+                room_code,
+                pot_id: _,
+            } => Some((*room_code, Permission::ManageStakes)),
             ClientMessage::CreateWager {
                 room_id,
                 name: _,
                 outcomes: _,
-            } => Some(Some(*room_id)),
+                wager_kind: _,
+                closes_at: _,
+            } => Some((*room_id, Permission::ManageStakes)),
             ClientMessage::JoinWager {
-                room_id: _,
+                room_id,
+                wager_id: _,
+                outcome_id: _,
+                amount: _,
+            } => Some((*room_id, Permission::Play)),
+            ClientMessage::ChangeBet {
+                room_code,
                 wager_id: _,
                 outcome_id: _,
                 amount: _,
+            } => Some((*room_code, Permission::Play)),
+            ClientMessage::WithdrawBet {
+                room_code,
+                wager_id: _,
+                outcome_id: _,
+            } => Some((*room_code, Permission::Play)),
+            ClientMessage::LockWager {
+                room_code,
+                wager_id: _,
+                closes_at: _,
+            } => Some((*room_code, Permission::ManageStakes)),
+            ClientMessage::ResolveWager {
+                room_id,
+                wager_id: _,
+                outcome_id: _,
+            } => Some((*room_id, Permission::ManageStakes)),
+            ClientMessage::CancelWager {
+                room_code,
+                wager_id: _,
+            } => Some((*room_code, Permission::ManageStakes)),
+            ClientMessage::RequestHistory {
+                room_code: _,
+                offset: _,
+                limit: _,
             } => None,
+            ClientMessage::Undo {
+                room_code,
+                count: _,
+            } => Some((*room_code, Permission::ManageRoom)),
+            ClientMessage::UpdateRoomConfig {
+                room_code,
+                changes: _,
+            } => Some((*room_code, Permission::ManageRoom)),
+            ClientMessage::GrantRole {
+                room_code,
+                id: _,
+                role: _,
+            } => Some((*room_code, Permission::ManageRoles)),
+            ClientMessage::RevokeRole { room_code, id: _ } => {
+                Some((*room_code, Permission::ManageRoles))
+            }
+            ClientMessage::Debug => None,
+        }
+    }
+    //The room a message concerns, replies to peers go to whoever else is in it
+    pub fn room_code(&self) -> Option<RoomCode> {
+        match self {
+            ClientMessage::CreateRoom {
+                code,
+                admin_pass: _,
+                config: _,
+            } => Some(*code),
+            ClientMessage::CreateGeneratedRoom {
+                admin_pass: _,
+                config: _,
+            } => None,
+            ClientMessage::JoinRoom { code, name: _ } => Some(*code),
+            ClientMessage::Rejoin { code, token: _ } => Some(*code),
+            ClientMessage::WatchRoom { code } => Some(*code),
+            ClientMessage::LeaveRoom { room_code } => Some(*room_code),
+            ClientMessage::RemoveFromRoom { code, id: _ } => Some(*code),
+            ClientMessage::DeleteRoom { room_code: code } => Some(*code),
+            ClientMessage::RequestAdmin { room, password: _ } => Some(*room),
+            ClientMessage::BlessScore {
+                room_code,
+                to: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::RemoveScore {
+                room_code,
+                from: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::GiveScore {
+                room_code,
+                to: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::TransferScore {
+                room_code,
+                from: _,
+                to: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::CreatePot {
+                room_code,
+                score_requirement: _,
+                description: _,
+                closes_at: _,
+                variable_buy_in: _,
+            } => Some(*room_code),
+            ClientMessage::JoinPot {
+                room_code,
+                pot_id: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::LockPot {
+                room_code,
+                pot_id: _,
+                closes_at: _,
+            } => Some(*room_code),
+            ClientMessage::ResolvePot {
+                room_id,
+                pot_id: _,
+                winners: _,
+                split: _,
+                allow_outsiders: _,
+            } => Some(*room_id),
+            ClientMessage::DrawPot {
+                room_code,
+                pot_id: _,
+                weighted: _,
+            } => Some(*room_code),
+            ClientMessage::CancelPot {
+                room_code,
+                pot_id: _,
+            } => Some(*room_code),
+            ClientMessage::CreateWager {
+                room_id,
+                name: _,
+                outcomes: _,
+                wager_kind: _,
+                closes_at: _,
+            } => Some(*room_id),
+            ClientMessage::JoinWager {
+                room_id,
+                wager_id: _,
+                outcome_id: _,
+                amount: _,
+            } => Some(*room_id),
+            ClientMessage::ChangeBet {
+                room_code,
+                wager_id: _,
+                outcome_id: _,
+                amount: _,
+            } => Some(*room_code),
+            ClientMessage::WithdrawBet {
+                room_code,
+                wager_id: _,
+                outcome_id: _,
+            } => Some(*room_code),
+            ClientMessage::LockWager {
+                room_code,
+                wager_id: _,
+                closes_at: _,
+            } => Some(*room_code),
             ClientMessage::ResolveWager {
                 room_id,
                 wager_id: _,
                 outcome_id: _,
-            } => Some(Some(*room_id)),
+            } => Some(*room_id),
+            ClientMessage::CancelWager {
+                room_code,
+                wager_id: _,
+            } => Some(*room_code),
+            ClientMessage::RequestHistory {
+                room_code,
+                offset: _,
+                limit: _,
+            } => Some(*room_code),
+            ClientMessage::Undo {
+                room_code,
+                count: _,
+            } => Some(*room_code),
+            ClientMessage::UpdateRoomConfig {
+                room_code,
+                changes: _,
+            } => Some(*room_code),
+            ClientMessage::GrantRole {
+                room_code,
+                id: _,
+                role: _,
+            } => Some(*room_code),
+            ClientMessage::RevokeRole { room_code, id: _ } => Some(*room_code),
             ClientMessage::Debug => None,
         }
     }
@@ -18,7 +18,7 @@ use tokio_tungstenite::WebSocketStream;
 
 use crate::{
     connection::message::Destination,
-    state::{ServerState, error::MessageHandleError},
+    state::{ServerState, error::MessageHandleError, room::RoomCode},
 };
 pub type WSStream = WebSocketStream<TcpStream>;
 pub type WSMessage = tokio_tungstenite::tungstenite::Message;
@@ -50,7 +50,7 @@ impl Connection {
             addr,
             rx,
             sink,
-            stream: stream,
+            stream,
             server_state,
         }
     }
@@ -59,14 +59,14 @@ impl Connection {
             log::trace!("Message recieved!: {msg}");
             match ClientMessage::try_from(msg) {
                 Ok(msg) => {
-                    if let Some(room_code) = msg.requires_admin() {
+                    if let Some((room_code, permission)) = msg.required_permission() {
                         match self
                             .server_state
                             .read()
-                            .is_user_admin(&self.addr, room_code)
+                            .user_has_permission(&self.addr, room_code, permission)
                         {
-                            Ok(is_admin) => {
-                                if !is_admin {
+                            Ok(permitted) => {
+                                if !permitted {
                                     Self::forward_error_to_client(
                                         self.server_state.clone(),
                                         self.addr,
@@ -86,20 +86,19 @@ impl Connection {
                             }
                         }
                     }
+                    let room_code = msg.room_code();
                     let errors = match self.server_state.read().handle_message(msg, self.addr) {
                         Ok(responses) => responses
                             .into_iter()
                             .filter_map(|(response, dest)| {
-                                if let Err(es) = Self::send_message(
+                                Self::send_message(
                                     self.server_state.clone(),
                                     &self.addr,
+                                    room_code,
                                     response,
                                     dest,
-                                ) {
-                                    Some(es)
-                                } else {
-                                    None
-                                }
+                                )
+                                .err()
                             })
                             .flatten()
                             .collect_vec(),
@@ -149,6 +148,7 @@ impl Connection {
     fn send_message(
         server_state: Arc<RwLock<ServerState>>,
         self_addr: &SocketAddr,
+        room_code: Option<RoomCode>,
         msg: ServerMessage,
         dest: Destination,
     ) -> Result<(), Vec<MessageSendError>> {
@@ -158,12 +158,12 @@ impl Connection {
                 .read()
                 .send_to_addr(self_addr, msg)
                 .map_err(|x| vec![x]),
-            message::Destination::PeersExclusive => {
-                server_state.read().send_to_peers(self_addr, msg, false)
-            }
-            message::Destination::PeersInclusive => {
-                server_state.read().send_to_peers(self_addr, msg, true)
-            }
+            message::Destination::PeersExclusive => server_state
+                .read()
+                .send_to_peers(self_addr, room_code, msg, false),
+            message::Destination::PeersInclusive => server_state
+                .read()
+                .send_to_peers(self_addr, room_code, msg, true),
             message::Destination::Specific(socket_addr) => server_state
                 .read()
                 .send_to_addr(&socket_addr, msg)
@@ -2,5 +2,6 @@
 pub mod connection;
 pub mod serialization;
 pub mod state;
+pub mod tasks;
 #[cfg(test)]
 pub mod tests;
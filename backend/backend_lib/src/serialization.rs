@@ -9,7 +9,7 @@ impl JsonSchema for RoomCode {
         "RoomCode".into()
     }
 
-    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
         json_schema!({
             "type": "string",
             "min_length": "8",
@@ -56,7 +56,7 @@ impl<'de> Visitor<'de> for RoomCodeVisitor {
             Err(err)
         }
     }
-    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    fn visit_newtype_struct<D>(self, _deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
@@ -67,6 +67,47 @@ impl<'de> Visitor<'de> for RoomCodeVisitor {
         formatter.write_str("An alpha numeric string of length 8")
     }
 }
+
+//Used through #[serde(with)] so room password hashes survive a restart
+pub mod password_hash_string {
+    use password_hash::PasswordHashString;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S>(hash: &PasswordHashString, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(hash.as_str())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<PasswordHashString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hash = String::deserialize(deserializer)?;
+        PasswordHashString::new(&hash).map_err(D::Error::custom)
+    }
+}
+
+pub mod salt_string {
+    use password_hash::SaltString;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S>(salt: &SaltString, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(salt.as_str())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SaltString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let salt = String::deserialize(deserializer)?;
+        SaltString::from_b64(&salt).map_err(D::Error::custom)
+    }
+}
 #[cfg(test)]
 mod tests {
     use crate::state::room::RoomCode;
@@ -12,8 +12,6 @@ pub enum MessageHandleError {
     NonexistentRoom(RoomCode),
     #[error("Room with code {0} already exists")]
     RoomAlreadyExists(RoomCode),
-    #[error("User from socket: {0} is not in a room")]
-    UserNotInAnyRoom(SocketAddr),
     #[error("Session from socket: {0} does not exist")]
     NonexistentSession(SocketAddr),
     #[error("Could not mutate room: {0}")]
@@ -22,7 +20,7 @@ pub enum MessageHandleError {
     PotMutationError(PotMutationError),
     #[error("Could not mutate wager: {0}")]
     WagerMutationError(WagerMutationError),
-    #[error("This user is not an admin.")]
+    #[error("This user doesn't have permission to do that.")]
     AuthorizationError,
 }
 
@@ -32,7 +30,6 @@ impl MessageHandleError {
             MessageHandleError::NonexistentRoom(_) | MessageHandleError::RoomAlreadyExists(_) => {
                 true
             }
-            MessageHandleError::UserNotInAnyRoom(_) => true,
             MessageHandleError::NonexistentSession(_) => false,
             MessageHandleError::RoomMutationError(room_mutation_error) => match room_mutation_error
             {
@@ -46,6 +43,13 @@ impl MessageHandleError {
                     wager_id: _,
                     room_code: _,
                 } => false,
+                RoomMutationError::InvalidToken(_) => true,
+                RoomMutationError::NothingToUndo(_) => true,
+                RoomMutationError::RoomFull(_) => true,
+                RoomMutationError::NameTaken(_) => true,
+                RoomMutationError::GivingDisabled(_) => true,
+                RoomMutationError::TransfersDisabled(_) => true,
+                RoomMutationError::InvalidConfig(_) => true,
             },
             MessageHandleError::PotMutationError(pot_mutation_error) => match pot_mutation_error {
                 PotMutationError::UserAlreadyExists {
@@ -62,6 +66,18 @@ impl MessageHandleError {
                     user_score: _,
                     score_req: _,
                 } => true,
+                PotMutationError::PotLocked(_) => true,
+                PotMutationError::InvalidSplit(_) => true,
+                PotMutationError::WinnerNotParticipant {
+                    user_id: _,
+                    pot_id: _,
+                } => true,
+                PotMutationError::NoEntrants(_) => true,
+                PotMutationError::BelowMinimum {
+                    pot_id: _,
+                    amount: _,
+                    minimum: _,
+                } => true,
             },
             MessageHandleError::WagerMutationError(wager_mutation_error) => {
                 match wager_mutation_error {
@@ -77,6 +93,12 @@ impl MessageHandleError {
                         user_id: _,
                         outcome_id: _,
                     } => false,
+                    WagerMutationError::InvalidHouseCut(_) => true,
+                    WagerMutationError::WagerLocked(_) => true,
+                    WagerMutationError::BetNotFound {
+                        user_id: _,
+                        outcome_id: _,
+                    } => false,
                 }
             }
             MessageHandleError::AuthorizationError => true,
@@ -167,6 +189,20 @@ pub enum RoomMutationError {
         wager_id: usize,
         room_code: RoomCode,
     },
+    #[error("Could not rejoin room {0}, the member may have been removed")]
+    InvalidToken(RoomCode),
+    #[error("There is nothing left to undo in room {0}")]
+    NothingToUndo(RoomCode),
+    #[error("Room {0} is full")]
+    RoomFull(RoomCode),
+    #[error("Someone is already called {0}")]
+    NameTaken(String),
+    #[error("Giving score is turned off in room {0}")]
+    GivingDisabled(RoomCode),
+    #[error("Transferring score is turned off in room {0}")]
+    TransfersDisabled(RoomCode),
+    #[error("Room {0} needs a credit limit of at least 0 that covers the starting balance")]
+    InvalidConfig(RoomCode),
 }
 
 impl From<AdminRequestError> for RoomMutationError {
@@ -189,6 +225,20 @@ pub enum PotMutationError {
         user_score: i64,
         score_req: i64,
     },
+    #[error("Pot {0} isn't taking any more participants")]
+    PotLocked(ID),
+    #[error("Pot {0} needs distinct winners and percentages that add up to 100")]
+    InvalidSplit(ID),
+    #[error("User with id: {user_id} can't win pot {pot_id} without joining it")]
+    WinnerNotParticipant { user_id: ID, pot_id: ID },
+    #[error("Nobody in the room has entered pot {0}")]
+    NoEntrants(ID),
+    #[error("Pot {pot_id} needs at least {minimum} to join, {amount} isn't enough")]
+    BelowMinimum {
+        pot_id: ID,
+        amount: i64,
+        minimum: i64,
+    },
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -199,4 +249,30 @@ pub enum WagerMutationError {
     NonexistentOutcome { outcome_id: ID, wager_id: ID },
     #[error("User with id {user_id} already chose outcome {outcome_id}")]
     UserAlreadyChose { user_id: usize, outcome_id: usize },
+    #[error("A house cut of {0}% is more than the whole pool")]
+    InvalidHouseCut(u8),
+    #[error("Wager {0} isn't taking any more bets")]
+    WagerLocked(ID),
+    #[error("User with id {user_id} has no bet on outcome {outcome_id}")]
+    BetNotFound { user_id: ID, outcome_id: ID },
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Could not access room storage: {0}")]
+    Io(std::io::Error),
+    #[error("Could not (de)serialize rooms: {0}")]
+    Serialization(serde_json::Error),
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(v: std::io::Error) -> Self {
+        Self::Io(v)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(v: serde_json::Error) -> Self {
+        Self::Serialization(v)
+    }
 }
@@ -2,8 +2,17 @@ pub mod error;
 pub mod room;
 pub mod server_state;
 pub mod session_data;
+pub mod storage;
 pub use room::Room;
 pub use server_state::ServerState;
 pub use session_data::SessionData;
 
 pub type ID = usize;
+
+//Milliseconds since the unix epoch
+pub fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("System time should be after the unix epoch")
+        .as_millis() as u64
+}
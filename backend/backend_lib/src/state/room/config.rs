@@ -0,0 +1,87 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::state::room::StakePolicy;
+
+//The most a room can let members go into debt, the largest integer a javascript number holds exactly
+pub const MAX_CREDIT_LIMIT: i64 = (1 << 53) - 1;
+
+//Missing fields fall back to their defaults when a room is created, use RoomConfigPatch to change an existing one
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RoomConfig {
+    //What every new member's score starts at
+    pub starting_balance: i64,
+    //How far below zero members can go, 0 keeps every score positive
+    pub credit_limit: i64,
+    //None for no limit
+    pub max_members: Option<usize>,
+    pub allow_give: bool,
+    pub allow_transfer: bool,
+    pub stake_policy: StakePolicy,
+    //Whether pot winners have to be picked from the pot's participants
+    pub strict_pot_winners: bool,
+    //Names are compared ignoring case and surrounding whitespace
+    pub unique_names: bool,
+}
+
+impl Default for RoomConfig {
+    fn default() -> Self {
+        Self {
+            starting_balance: 0,
+            credit_limit: 0,
+            max_members: None,
+            allow_give: true,
+            allow_transfer: true,
+            stake_policy: StakePolicy::default(),
+            strict_pot_winners: true,
+            unique_names: false,
+        }
+    }
+}
+
+//Only the fields that are set get changed, everything else keeps the room's current value
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RoomConfigPatch {
+    pub starting_balance: Option<i64>,
+    pub credit_limit: Option<i64>,
+    //Some(None) lifts the limit, so a null has to be told apart from the field being missing
+    #[serde(deserialize_with = "present")]
+    pub max_members: Option<Option<usize>>,
+    pub allow_give: Option<bool>,
+    pub allow_transfer: Option<bool>,
+    pub stake_policy: Option<StakePolicy>,
+    pub strict_pot_winners: Option<bool>,
+    pub unique_names: Option<bool>,
+}
+
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl RoomConfig {
+    pub fn patched(&self, patch: RoomConfigPatch) -> Self {
+        Self {
+            starting_balance: patch.starting_balance.unwrap_or(self.starting_balance),
+            credit_limit: patch.credit_limit.unwrap_or(self.credit_limit),
+            max_members: patch.max_members.unwrap_or(self.max_members),
+            allow_give: patch.allow_give.unwrap_or(self.allow_give),
+            allow_transfer: patch.allow_transfer.unwrap_or(self.allow_transfer),
+            stake_policy: patch.stake_policy.unwrap_or(self.stake_policy),
+            strict_pot_winners: patch.strict_pot_winners.unwrap_or(self.strict_pot_winners),
+            unique_names: patch.unique_names.unwrap_or(self.unique_names),
+        }
+    }
+    pub fn is_valid(&self) -> bool {
+        (0..=MAX_CREDIT_LIMIT).contains(&self.credit_limit)
+            && self.starting_balance >= self.score_floor()
+    }
+    //The lowest score a member can be brought down to
+    pub fn score_floor(&self) -> i64 {
+        -self.credit_limit
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::state::{ID, timestamp};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, schemars::JsonSchema)]
+#[serde(tag = "kind")]
+pub enum LedgerAction {
+    Bless,
+    Remove,
+    Give,
+    Transfer,
+    PotStake { pot_id: ID },
+    PotPayout { pot_id: ID },
+    WagerStake { wager_id: ID },
+    WagerPayout { wager_id: ID },
+    PotRefund { pot_id: ID },
+    WagerRefund { wager_id: ID },
+    BetWithdrawal { wager_id: ID },
+    Undo,
+}
+
+//One entry per score change, a transfer between two members is two entries
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
+pub struct LedgerEntry {
+    id: ID,
+    action: LedgerAction,
+    //Whoever caused the change, None if they weren't a member of the room
+    actor: Option<ID>,
+    target: ID,
+    amount: i64,
+    timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn record(&mut self, action: LedgerAction, actor: Option<ID>, target: ID, amount: i64) {
+        let id = self.entries.len();
+        self.entries.push(LedgerEntry {
+            id,
+            action,
+            actor,
+            target,
+            amount,
+            timestamp: timestamp(),
+        });
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    //Newest first, so offset 0 is always the latest activity
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<LedgerEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl LedgerEntry {
+    pub fn summary(&self) -> (LedgerAction, Option<ID>, ID, i64) {
+        (self.action, self.actor, self.target, self.amount)
+    }
+}
@@ -1,26 +1,33 @@
 use std::collections::HashSet;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::state::{ID, error::RoomMutationError};
+use crate::state::{ID, error::RoomMutationError, room::role::Role};
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
 pub struct MemberState {
     id: ID,
     name: String,
     score: i64,
+    //How far below zero the score is, kept alongside it so clients don't have to work it out
+    #[serde(default)]
+    debt: i64,
     current_wagers: HashSet<ID>,
     current_pots: HashSet<ID>,
+    #[serde(default)]
+    role: Role,
 }
 
 impl MemberState {
-    pub fn new(name: String, id: ID) -> Self {
+    pub fn new(name: String, id: ID, score: i64) -> Self {
         Self {
             name,
-            score: 0,
+            score,
+            debt: (-score).max(0),
             id,
             current_wagers: HashSet::new(),
             current_pots: HashSet::new(),
+            role: Role::default(),
         }
     }
 
@@ -28,18 +35,41 @@ impl MemberState {
         self.score
     }
 
-    pub fn set_score(&mut self, score: i64) -> Result<(), RoomMutationError> {
-        if score < 0 {
+    //Only moves that go further under the floor are refused, so members already below it can still be paid
+    pub fn set_score(&mut self, score: i64, floor: i64) -> Result<(), RoomMutationError> {
+        if score < floor && score < self.score {
             return Err(RoomMutationError::NegativeScore);
         }
         self.score = score;
+        self.debt = (-score).max(0);
         Ok(())
     }
 
+    pub fn debt(&self) -> i64 {
+        self.debt
+    }
+
+    //What the member can still put into pots and wagers, counting whatever credit they have left
+    pub fn available(&self, floor: i64) -> i64 {
+        self.score.saturating_sub(floor)
+    }
+
     pub fn id(&self) -> ID {
         self.id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn set_role(&mut self, role: Role) {
+        self.role = role;
+    }
+
     pub fn current_wagers_mut(&mut self) -> &mut HashSet<ID> {
         &mut self.current_wagers
     }
@@ -1,104 +1,390 @@
 use std::{
-    cell::OnceCell,
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{Debug, Display},
     hash::Hash,
     net::SocketAddr,
+    sync::LazyLock,
+    time::{Duration, Instant},
 };
 
 use argon2::Argon2;
+use itertools::Itertools;
 use password_hash::{PasswordHashString, PasswordHasher, SaltString};
-use rand::rngs::OsRng;
+use rand::{Rng, RngCore, SeedableRng, distributions::Alphanumeric, rngs::OsRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::state::{
     ID,
-    error::{PotMutationError, RoomMutationError, StateMutationError},
-    room::{pot::Pot, wager::Wager},
+    error::{PotMutationError, RoomMutationError, StateMutationError, WagerMutationError},
+    room::{
+        config::{RoomConfig, RoomConfigPatch},
+        ledger::{Ledger, LedgerAction, LedgerEntry},
+        pot::{Pot, PotSplit},
+        role::{Permission, Role},
+        wager::{Wager, WagerKind},
+    },
+    timestamp,
 };
 
 use super::error::AdminRequestError;
 
+pub mod config;
+pub mod ledger;
 mod member_state;
 pub mod pot;
+pub mod role;
 pub mod wager;
 pub use member_state::MemberState;
 
+static HASHER: LazyLock<Argon2<'static>> = LazyLock::new(Argon2::default);
+
+pub type ScoreUpdate = (ID, i64);
+//A pot or wager along with the deadline it closed at
+pub type Closure = (ID, Option<u64>);
+
+const MEMBER_TOKEN_LENGTH: usize = 32;
+//Leaves out 0/O and 1/I so codes can be read out loud and typed back in
+const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_UNDO_DEPTH: usize = 20;
+
+//The entries a score affecting mutation might touch as they were before it, None for ones that
+//didn't exist yet
+#[derive(Debug, Clone)]
+struct Snapshot {
+    members: HashMap<ID, Option<MemberState>>,
+    pots: HashMap<ID, Option<Pot>>,
+    wagers: HashMap<ID, Option<Wager>>,
+}
+
+//What an undo needs to put back, only the entries the mutation actually changed so anything
+//changed since without a checkpoint is left alone
+#[derive(Debug, Clone, Default)]
+struct Checkpoint {
+    //None for entries the mutation created
+    members: HashMap<ID, Option<MemberState>>,
+    pots: HashMap<ID, Option<Pot>>,
+    wagers: HashMap<ID, Option<Wager>>,
+    //Members who left after the mutation and the policy their stakes were settled under
+    departures: Vec<(ID, StakePolicy)>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct UndoOutcome {
+    pub undone: usize,
+    pub scores: Vec<ScoreUpdate>,
+    pub restored_pots: Vec<Pot>,
+    pub removed_pots: Vec<ID>,
+    pub restored_wagers: Vec<Wager>,
+    pub removed_wagers: Vec<ID>,
+}
+
+//What happens to a member's stakes when they leave or are removed
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, schemars::JsonSchema,
+)]
+pub enum StakePolicy {
+    //The stake disappears from the pot or wager entirely
+    Void,
+    //The stake stays where it is, under the departed member's id
+    Keep,
+    //The stake stays in the pot or wager and goes to whoever wins it
+    #[default]
+    Forfeit,
+}
+
+//Everything needed to check a raffle was fair
+#[derive(Debug, PartialEq)]
+pub struct Draw {
+    pub seed: u64,
+    pub winner: ID,
+    pub scores: Vec<ScoreUpdate>,
+}
+
+//Resolved pots and wagers are removed outright, so they don't need a status
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, schemars::JsonSchema,
+)]
+pub enum BettingStatus {
+    #[default]
+    Open,
+    Locked,
+}
+
+//Whether a pot or wager is still taking stakes
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, schemars::JsonSchema,
+)]
+#[serde(default)]
+pub struct Betting {
+    status: BettingStatus,
+    //Unix millis after which nothing more is taken, even if it hasn't been locked yet
+    closes_at: Option<u64>,
+}
+impl Betting {
+    pub fn is_open(&self) -> bool {
+        let past_deadline = self.closes_at.is_some_and(|t| timestamp() >= t);
+        self.status == BettingStatus::Open && !past_deadline
+    }
+    //Locks now, or at closes_at if it's still in the future
+    pub fn lock(&mut self, closes_at: Option<u64>) {
+        match closes_at {
+            Some(t) if t > timestamp() => self.closes_at = Some(t),
+            _ => self.status = BettingStatus::Locked,
+        }
+    }
+    //Flips to locked once the deadline has passed by now, returns whether it did
+    pub fn close_if_due(&mut self, now: u64) -> bool {
+        let due = self.status == BettingStatus::Open && self.closes_at.is_some_and(|t| now >= t);
+        if due {
+            self.status = BettingStatus::Locked;
+        }
+        due
+    }
+    pub fn status(&self) -> BettingStatus {
+        self.status
+    }
+    pub fn closes_at(&self) -> Option<u64> {
+        self.closes_at
+    }
+}
+
+//Pots and wagers are only included if the departure actually changed them
+#[derive(Debug)]
+pub struct Departure {
+    pub id: ID,
+    pub addr: Option<SocketAddr>,
+    pub pots: Vec<Pot>,
+    pub wagers: Vec<Wager>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Disconnection {
+    since: Instant,
+}
+
+//Connection bound fields are skipped, they're meaningless after a restart
+#[derive(Serialize, Deserialize)]
 pub struct Room {
     room_code: RoomCode,
     members: HashMap<ID, MemberState>,
+    #[serde(skip)]
     address_map: HashMap<SocketAddr, ID>,
+    //Sessions that get the room's broadcasts without being members
+    #[serde(skip)]
+    watchers: HashSet<SocketAddr>,
     next_member_id: ID,
+    #[serde(with = "crate::serialization::password_hash_string")]
     admin_pass_hash: password_hash::PasswordHashString,
+    #[serde(with = "crate::serialization::salt_string")]
     admin_pass_salt: password_hash::SaltString,
-    admins: HashSet<SocketAddr>,
+    member_tokens: HashMap<String, ID>,
+    #[serde(skip)]
+    disconnected: HashMap<ID, Disconnection>,
     pots: HashMap<ID, Pot>,
     next_pot_id: usize,
     wagers: HashMap<ID, Wager>,
     next_wager_id: usize,
+    ledger: Ledger,
+    #[serde(skip)]
+    checkpoints: VecDeque<Checkpoint>,
+    #[serde(default)]
+    config: RoomConfig,
+    //Unix millis, rooms saved before this was tracked count as created when they're loaded
+    #[serde(default = "timestamp")]
+    created_at: u64,
+    #[serde(skip)]
+    empty_since: Option<Instant>,
 }
 impl Room {
-    const HASHER: OnceCell<Argon2<'_>> = OnceCell::new();
-    pub fn new(room_code: RoomCode, admin_pass: &str) -> Self {
+    pub fn new(room_code: RoomCode, admin_pass: &str, config: RoomConfig) -> Self {
         let (hash, salt) = Room::hash_pass(admin_pass);
         Self {
             room_code,
             members: HashMap::new(),
             next_member_id: 0,
             address_map: HashMap::new(),
-            admins: HashSet::new(),
+            watchers: HashSet::new(),
+            member_tokens: HashMap::new(),
+            disconnected: HashMap::new(),
             admin_pass_hash: hash,
             admin_pass_salt: salt,
             pots: HashMap::new(),
             next_pot_id: 0,
             wagers: HashMap::new(),
             next_wager_id: 0,
+            ledger: Ledger::default(),
+            checkpoints: VecDeque::new(),
+            config,
+            created_at: timestamp(),
+            empty_since: Some(Instant::now()),
         }
     }
     pub fn get_addresses(&self) -> impl Iterator<Item = &SocketAddr> {
-        self.address_map.keys()
+        self.address_map.keys().chain(self.watchers.iter())
+    }
+    pub fn add_watcher(&mut self, addr: SocketAddr) -> Result<(), RoomMutationError> {
+        if self.address_map.contains_key(&addr) || !self.watchers.insert(addr) {
+            return Err(RoomMutationError::UserAlreadyExists(addr, self.room_code));
+        }
+        Ok(())
+    }
+    pub fn remove_watcher(&mut self, addr: &SocketAddr) -> bool {
+        self.watchers.remove(addr)
     }
 
     pub fn add_user(
         &mut self,
         user_addr: SocketAddr,
         name: String,
-    ) -> Result<ID, RoomMutationError> {
+    ) -> Result<(ID, String), RoomMutationError> {
         if self.address_map.contains_key(&user_addr) {
             Err(RoomMutationError::UserAlreadyExists(
                 user_addr,
                 self.room_code,
             ))
+        } else if self
+            .config
+            .max_members
+            .is_some_and(|max| self.members.len() >= max)
+        {
+            Err(RoomMutationError::RoomFull(self.room_code))
+        } else if self.config.unique_names
+            && self
+                .members
+                .values()
+                .any(|member| member.name().trim().to_lowercase() == name.trim().to_lowercase())
+        {
+            Err(RoomMutationError::NameTaken(name))
         } else {
-            let id = self.next_member_id.clone();
+            let id = self.next_member_id;
+            self.empty_since = None;
+            //Watchers can join in, they just stop being watchers
+            self.watchers.remove(&user_addr);
             self.address_map.insert(user_addr, id);
-            self.members.insert(id, MemberState::new(name, id));
+            self.members
+                .insert(id, MemberState::new(name, id, self.config.starting_balance));
             self.next_member_id += 1;
-            Ok(id)
+            let token: String = OsRng
+                .sample_iter(&Alphanumeric)
+                .take(MEMBER_TOKEN_LENGTH)
+                .map(char::from)
+                .collect();
+            self.member_tokens.insert(token.clone(), id);
+            Ok((id, token))
+        }
+    }
+    //Rebinds a member to a new connection, stealing it from the old one if it's still around
+    pub fn rejoin_user(
+        &mut self,
+        user_addr: SocketAddr,
+        token: &str,
+    ) -> Result<ID, RoomMutationError> {
+        let id = *self
+            .member_tokens
+            .get(token)
+            .ok_or(RoomMutationError::InvalidToken(self.room_code))?;
+        if self.address_map.contains_key(&user_addr) {
+            return Err(RoomMutationError::UserAlreadyExists(
+                user_addr,
+                self.room_code,
+            ));
+        }
+        if let Some(old_addr) = self.address_of(id) {
+            self.address_map.remove(&old_addr);
+        }
+        self.disconnected.remove(&id);
+        self.watchers.remove(&user_addr);
+        self.address_map.insert(user_addr, id);
+        Ok(id)
+    }
+    //Unlike remove_user, the member and their stakes stay put so they can rejoin
+    pub fn disconnect_user(&mut self, user_addr: &SocketAddr) -> Option<ID> {
+        let id = self.address_map.remove(user_addr)?;
+        self.disconnected.insert(
+            id,
+            Disconnection {
+                since: Instant::now(),
+            },
+        );
+        Some(id)
+    }
+    //Members restored from storage have no connection, start their grace period now
+    pub fn disconnect_unbound_users(&mut self) {
+        let now = Instant::now();
+        if self.members.is_empty() {
+            self.empty_since = Some(now);
+        }
+        for id in self.members.keys() {
+            if self.address_of(*id).is_none() {
+                self.disconnected
+                    .entry(*id)
+                    .or_insert(Disconnection { since: now });
+            }
         }
     }
-    pub fn remove_user(&mut self, user: ID) -> Result<SocketAddr, RoomMutationError> {
+    pub fn expire_disconnected_users(&mut self, grace_period: Duration) -> Vec<Departure> {
+        let expired = self
+            .disconnected
+            .iter()
+            .filter(|(_, disconnection)| disconnection.since.elapsed() >= grace_period)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        expired
+            .into_iter()
+            .filter_map(|id| {
+                log::trace!("Rejoin grace period expired for {id} in {}", self.room_code);
+                self.remove_user(id)
+                    .inspect_err(|e| log::error!("{e}"))
+                    .ok()
+            })
+            .collect()
+    }
+    fn address_of(&self, user: ID) -> Option<SocketAddr> {
+        self.address_map
+            .iter()
+            .find(|(_, id)| **id == user)
+            .map(|(addr, _)| *addr)
+    }
+    pub fn remove_user(&mut self, user: ID) -> Result<Departure, RoomMutationError> {
         if let Some(mut user_state) = self.members.remove(&user) {
-            for wager_id in user_state.current_wagers_mut().iter() {
-                if let Some(wager) = self.wagers.get_mut(wager_id) {
-                    wager.remove_user(user);
+            let mut wagers = Vec::new();
+            for wager_id in user_state.current_wagers_mut().iter().sorted() {
+                if let Some(wager) = self.wagers.get_mut(wager_id)
+                    && wager.remove_user(user, self.config.stake_policy)
+                {
+                    wagers.push(wager.clone());
                 }
             }
-            for pot_id in user_state.current_pots_mut().iter() {
-                if let Some(pot) = self.pots.get_mut(pot_id) {
-                    let _ = pot.remove_user(user);
+            let mut pots = Vec::new();
+            for pot_id in user_state.current_pots_mut().iter().sorted() {
+                if let Some(pot) = self.pots.get_mut(pot_id)
+                    && pot
+                        .remove_user(user, self.config.stake_policy)
+                        .inspect_err(|e| log::error!("{e}"))
+                        .unwrap_or(false)
+                {
+                    pots.push(pot.clone());
                 }
             }
-            if let Some(addr) = self
-                .address_map
-                .iter()
-                .find(|(_, id)| **id == user)
-                .map(|(addr, _)| *addr)
-            {
+            self.member_tokens.retain(|_, id| *id != user);
+            self.disconnected.remove(&user);
+            if let Some(checkpoint) = self.checkpoints.back_mut() {
+                checkpoint.departures.push((user, self.config.stake_policy));
+            }
+            let addr = self.address_of(user);
+            if let Some(addr) = addr {
                 self.address_map.remove(&addr);
-                Ok(addr)
-            } else {
-                Err(RoomMutationError::UserNotInRoom(user, self.room_code))
             }
+            if self.members.is_empty() {
+                self.empty_since = Some(Instant::now());
+            }
+            Ok(Departure {
+                id: user,
+                addr,
+                pots,
+                wagers,
+            })
         } else {
             Err(RoomMutationError::UserNotInRoom(user, self.room_code))
         }
@@ -108,9 +394,7 @@ impl Room {
         (Self::hash_pass_with_salt(pass, &salt), salt)
     }
     fn hash_pass_with_salt(pass: &str, salt: &SaltString) -> PasswordHashString {
-        let hasher = Self::HASHER;
-        let hasher = hasher.get_or_init(|| Argon2::default());
-        hasher
+        HASHER
             .hash_password(pass.as_bytes(), salt)
             .expect("Hashing should succeed!")
             .into()
@@ -122,85 +406,176 @@ impl Room {
         );
         self.admin_pass_hash == Self::hash_pass_with_salt(pass, &self.admin_pass_salt)
     }
-    pub fn add_admin(&mut self, user: SocketAddr, pass: &str) -> Result<(), AdminRequestError> {
+    //The admin password makes whoever has it an owner, which is how a room gets its first one
+    pub fn claim_owner(&mut self, user: ID, pass: &str) -> Result<(), AdminRequestError> {
         if self.check_admin_pass(pass) {
-            if self.admins.insert(user) {
-                Ok(())
-            } else {
+            let member = self
+                .members
+                .get_mut(&user)
+                .expect("Claiming member should be in the room!");
+            if member.role() == Role::Owner {
                 Err(AdminRequestError::AlreadyAdmin)
+            } else {
+                member.set_role(Role::Owner);
+                Ok(())
             }
         } else {
             Err(AdminRequestError::IncorrectPassword)
         }
     }
-    pub fn bless_score(&mut self, to: &ID, amount: i64) -> Result<(ID, i64), RoomMutationError> {
+    pub fn set_role(&mut self, user: ID, role: Role) -> Result<(), RoomMutationError> {
+        self.members
+            .get_mut(&user)
+            .ok_or(RoomMutationError::UserNotInRoom(user, self.room_code))?
+            .set_role(role);
+        Ok(())
+    }
+    pub fn bless_score(
+        &mut self,
+        actor: Option<ID>,
+        to: &ID,
+        amount: i64,
+    ) -> Result<ScoreUpdate, RoomMutationError> {
+        self.adjust_score(LedgerAction::Bless, actor, to, amount)
+    }
+    pub fn remove_score(
+        &mut self,
+        actor: Option<ID>,
+        from: &ID,
+        amount: i64,
+    ) -> Result<ScoreUpdate, RoomMutationError> {
+        self.adjust_score(LedgerAction::Remove, actor, from, -amount)
+    }
+    fn adjust_score(
+        &mut self,
+        action: LedgerAction,
+        actor: Option<ID>,
+        to: &ID,
+        amount: i64,
+    ) -> Result<ScoreUpdate, RoomMutationError> {
+        let checkpoint = self.snapshot([*to], [], []);
         let user = self
             .members
             .get_mut(to)
             .ok_or(RoomMutationError::UserNotInRoom(*to, self.room_code))?;
-        user.set_score(user.score() + amount)?;
-        Ok((*to, user.score()))
+        user.set_score(user.score() + amount, self.config.score_floor())?;
+        let update = (*to, user.score());
+        self.push_checkpoint(checkpoint);
+        self.ledger.record(action, actor, *to, amount);
+        Ok(update)
     }
     pub fn transfer_score(
+        &mut self,
+        actor: Option<ID>,
+        from: &ID,
+        to: &ID,
+        amount: i64,
+    ) -> Result<(ScoreUpdate, ScoreUpdate), RoomMutationError> {
+        if !self.config.allow_transfer {
+            return Err(RoomMutationError::TransfersDisabled(self.room_code));
+        }
+        self.move_score(LedgerAction::Transfer, actor, from, to, amount)
+    }
+    pub fn give_score(
         &mut self,
         from: &ID,
         to: &ID,
         amount: i64,
-    ) -> Result<((ID, i64), (ID, i64)), RoomMutationError> {
+    ) -> Result<(ScoreUpdate, ScoreUpdate), RoomMutationError> {
+        if !self.config.allow_give {
+            return Err(RoomMutationError::GivingDisabled(self.room_code));
+        }
+        self.move_score(LedgerAction::Give, Some(*from), from, to, amount)
+    }
+    fn move_score(
+        &mut self,
+        action: LedgerAction,
+        actor: Option<ID>,
+        from: &ID,
+        to: &ID,
+        amount: i64,
+    ) -> Result<(ScoreUpdate, ScoreUpdate), RoomMutationError> {
         if !self.members.contains_key(to) {
             return Err(RoomMutationError::UserNotInRoom(*to, self.room_code));
         }
+        let checkpoint = self.snapshot([*from, *to], [], []);
         let from_state = self
             .members
-            .get_mut(&from)
+            .get_mut(from)
             .ok_or(RoomMutationError::UserNotInRoom(*from, self.room_code))?;
         let prev_from_score = from_state.score();
-        from_state.set_score(from_state.score() - amount)?;
-        let from_score = from_state.score().clone();
+        from_state.set_score(from_state.score() - amount, self.config.score_floor())?;
+        let from_score = from_state.score();
         let to_state = self
             .members
-            .get_mut(&to)
+            .get_mut(to)
             .expect("Already checked if key contained!");
 
-        if let Err(e) = to_state.set_score(to_state.score() + amount) {
+        if let Err(e) = to_state.set_score(to_state.score() + amount, self.config.score_floor()) {
             self.members
                 .get_mut(from)
                 .expect("Already checked from exists!")
-                .set_score(prev_from_score)
+                .set_score(prev_from_score, self.config.score_floor())
                 .expect("Prev from score should always be valid!");
             return Err(e);
         }
 
-        let to_score = to_state.score().clone();
+        let to_score = to_state.score();
+        self.push_checkpoint(checkpoint);
+        self.ledger.record(action, actor, *from, -amount);
+        self.ledger.record(action, actor, *to, amount);
         Ok(((*from, from_score), (*to, to_score)))
     }
-    pub fn create_pot(&mut self, score_requirement: i64, desc: String) -> Pot {
-        let id = self.next_pot_id.clone();
+    pub fn create_pot(
+        &mut self,
+        score_requirement: i64,
+        desc: String,
+        closes_at: Option<u64>,
+        variable_buy_in: bool,
+    ) -> Result<Pot, RoomMutationError> {
+        if score_requirement < 0 {
+            return Err(RoomMutationError::NegativeScore);
+        }
+        let checkpoint = self.snapshot([], [self.next_pot_id], []);
+        let id = self.next_pot_id;
         self.next_pot_id += 1;
-        self.pots.insert(id, Pot::new(id, score_requirement, desc));
-        self.pots.get(&id).unwrap().clone()
+        let mut pot = Pot::new(id, score_requirement, desc, variable_buy_in);
+        if closes_at.is_some() {
+            pot.betting_mut().lock(closes_at);
+        }
+        self.pots.insert(id, pot);
+        self.push_checkpoint(checkpoint);
+        Ok(self.pots.get(&id).unwrap().clone())
     }
-    pub fn add_user_to_pot(&mut self, user_id: ID, pot_id: ID) -> Result<i64, StateMutationError> {
+    //Returns what the user put in and their new score
+    pub fn add_user_to_pot(
+        &mut self,
+        user_id: ID,
+        pot_id: ID,
+        amount: Option<i64>,
+    ) -> Result<(i64, i64), StateMutationError> {
         //alas, no partial borrows
         let room_code = self.room_code;
-        let score_req = self
+        let checkpoint = self.snapshot([user_id], [pot_id], []);
+        let pot = self
             .pots
             .get(&pot_id)
-            .ok_or(RoomMutationError::NonexistentPot(pot_id, room_code))?
-            .score_req();
+            .ok_or(RoomMutationError::NonexistentPot(pot_id, room_code))?;
+        pot.ensure_open()?;
+        let score_req = pot.buy_in(amount)?;
 
         let user = self
             .members
             .get_mut(&user_id)
             .ok_or(RoomMutationError::UserNotInRoom(user_id, room_code))?;
 
-        if user.score() - score_req < 0 {
+        if user.available(self.config.score_floor()) < score_req {
             {
                 return Err(PotMutationError::InsufficientScore {
                     user_id,
                     pot_id,
                     user_score: user.score(),
-                    score_req: score_req,
+                    score_req,
                 }
                 .into());
             }
@@ -209,45 +584,197 @@ impl Room {
         self.pots
             .get_mut(&pot_id)
             .expect("We've already got the pot!")
-            .join(user_id)?;
-        user.set_score(user.score() - score_req).unwrap();
+            .join(user_id, score_req)?;
+        user.set_score(user.score() - score_req, self.config.score_floor())
+            .unwrap();
         user.current_pots_mut().insert(pot_id);
-        Ok(user.score())
+        let new_score = user.score();
+        self.push_checkpoint(checkpoint);
+        self.ledger.record(
+            LedgerAction::PotStake { pot_id },
+            Some(user_id),
+            user_id,
+            -score_req,
+        );
+        Ok((score_req, new_score))
     }
     pub fn resolve_pot(
         &mut self,
+        actor: Option<ID>,
         pot_id: ID,
-        winner_id: ID,
-    ) -> Result<(ID, i64), StateMutationError> {
+        winners: &[ID],
+        split: &PotSplit,
+        allow_outsiders: bool,
+    ) -> Result<Vec<ScoreUpdate>, StateMutationError> {
         let pot = self
             .pots
             .get(&pot_id)
             .ok_or(RoomMutationError::NonexistentPot(pot_id, self.room_code))?;
-        let winner = self
-            .members
-            .get_mut(&winner_id)
-            .ok_or(RoomMutationError::UserNotInRoom(winner_id, self.room_code))?;
-        winner.set_score(winner.score() + pot.resolve())?;
-        let winner_score = winner.score();
+        if let Some(missing) = winners.iter().find(|id| !self.members.contains_key(id)) {
+            return Err(RoomMutationError::UserNotInRoom(*missing, self.room_code).into());
+        }
+        if self.config.strict_pot_winners
+            && !allow_outsiders
+            && let Some(outsider) = winners.iter().find(|id| !pot.has_participant(**id))
+        {
+            return Err(PotMutationError::WinnerNotParticipant {
+                user_id: *outsider,
+                pot_id,
+            }
+            .into());
+        }
+        let payouts = pot.payouts(winners, split)?;
+        let checkpoint = self.snapshot(
+            winners.iter().chain(pot.participants()).copied(),
+            [pot_id],
+            [],
+        );
+        let mut out = Vec::new();
+        for (winner_id, payout) in payouts {
+            let winner = self
+                .members
+                .get_mut(&winner_id)
+                .expect("Already checked every winner exists");
+            winner
+                .set_score(winner.score() + payout, self.config.score_floor())
+                .expect("Payouts should never be negative!");
+            out.push((winner_id, winner.score()));
+            self.ledger
+                .record(LedgerAction::PotPayout { pot_id }, actor, winner_id, payout);
+        }
         for id in pot.participants() {
             if let Some(participant) = self.members.get_mut(id) {
                 participant.current_pots_mut().remove(&pot_id);
             }
         }
         self.pots.remove(&pot_id);
-
-        Ok((winner_id, winner_score))
+        self.push_checkpoint(checkpoint);
+        Ok(out)
+    }
+    //Raffles the pot off between participants still in the room, optionally weighted by stake
+    pub fn draw_pot(
+        &mut self,
+        actor: Option<ID>,
+        pot_id: ID,
+        weighted: bool,
+    ) -> Result<Draw, StateMutationError> {
+        let candidates = self
+            .pots
+            .get(&pot_id)
+            .ok_or(RoomMutationError::NonexistentPot(pot_id, self.room_code))?
+            .stakes()
+            .into_iter()
+            .filter(|(id, _)| self.members.contains_key(id))
+            .collect_vec();
+        if candidates.is_empty() {
+            return Err(PotMutationError::NoEntrants(pot_id).into());
+        }
+        //Kept within what a javascript number can hold exactly, so clients can replay it
+        let seed = OsRng.gen_range(0..1 << 53);
+        let weights = candidates
+            .iter()
+            .map(|(_, stake)| if weighted { *stake } else { 1 })
+            .collect_vec();
+        let winner = candidates[pick_by_weight(seed, &weights)].0;
+        let scores = self.resolve_pot(actor, pot_id, &[winner], &PotSplit::Equal, false)?;
+        Ok(Draw {
+            seed,
+            winner,
+            scores,
+        })
+    }
+    //Hands every participant still in the room their stake back. Stakes that can't go back to
+    //whoever put them in, forfeited or kept by departed members, are shared between the rest
+    pub fn cancel_pot(
+        &mut self,
+        actor: Option<ID>,
+        pot_id: ID,
+    ) -> Result<Vec<ScoreUpdate>, RoomMutationError> {
+        let pot = self
+            .pots
+            .get(&pot_id)
+            .ok_or(RoomMutationError::NonexistentPot(pot_id, self.room_code))?;
+        let checkpoint = self.snapshot(pot.participants().copied(), [pot_id], []);
+        let pot = self.pots.remove(&pot_id).expect("Already found the pot");
+        let refunds = pot
+            .participants()
+            .filter(|id| self.members.contains_key(id))
+            .sorted()
+            .map(|id| (*id, pot.contribution(*id)))
+            .collect_vec();
+        let out = self.refund(
+            LedgerAction::PotRefund { pot_id },
+            actor,
+            refunds,
+            pot.resolve(),
+        );
+        for member in self.members.values_mut() {
+            member.current_pots_mut().remove(&pot_id);
+        }
+        self.push_checkpoint(checkpoint);
+        Ok(out)
+    }
+    //Gives each member their stake back plus a share of whatever's left of the total by stake
+    fn refund(
+        &mut self,
+        action: LedgerAction,
+        actor: Option<ID>,
+        stakes: Vec<(ID, i64)>,
+        total: i64,
+    ) -> Vec<ScoreUpdate> {
+        let leftover = total - stakes.iter().map(|(_, stake)| stake).sum::<i64>();
+        if stakes.is_empty() && leftover != 0 {
+            log::warn!("Nobody left in {} to refund {leftover} to", self.room_code);
+        }
+        let weights = stakes.iter().map(|(_, stake)| *stake).collect_vec();
+        let mut out = Vec::new();
+        for ((id, stake), share) in stakes.into_iter().zip(share_out(leftover, &weights)) {
+            let member = self
+                .members
+                .get_mut(&id)
+                .expect("Only members are refunded");
+            member
+                .set_score(member.score() + stake + share, self.config.score_floor())
+                .expect("Refunds should never make a score negative!");
+            out.push((id, member.score()));
+            self.ledger.record(action, actor, id, stake + share);
+        }
+        out
+    }
+    pub fn lock_pot(
+        &mut self,
+        pot_id: ID,
+        closes_at: Option<u64>,
+    ) -> Result<(BettingStatus, Option<u64>), RoomMutationError> {
+        let pot = self
+            .pots
+            .get_mut(&pot_id)
+            .ok_or(RoomMutationError::NonexistentPot(pot_id, self.room_code))?;
+        pot.betting_mut().lock(closes_at);
+        Ok((pot.betting().status(), pot.betting().closes_at()))
     }
     pub fn create_wager(
         &mut self,
         description: String,
         outcomes: Vec<wager::WagerOutcome>,
-    ) -> Wager {
-        let id = self.next_wager_id.clone();
+        kind: WagerKind,
+        closes_at: Option<u64>,
+    ) -> Result<Wager, WagerMutationError> {
+        if let WagerKind::Pool { house_cut_percent } = kind
+            && house_cut_percent > 100
+        {
+            return Err(WagerMutationError::InvalidHouseCut(house_cut_percent));
+        }
+        let checkpoint = self.snapshot([], [], [self.next_wager_id]);
+        let id = self.next_wager_id;
         self.next_wager_id += 1;
-        self.wagers
-            .insert(id, Wager::new(id, description, outcomes));
-        self.wagers.get(&id).expect("Just created wager!").clone()
+        let mut wager = Wager::new(id, description, outcomes, kind);
+        if closes_at.is_some() {
+            wager.betting_mut().lock(closes_at);
+        }
+        self.wagers.insert(id, wager);
+        self.push_checkpoint(checkpoint);
+        Ok(self.wagers.get(&id).expect("Just created wager!").clone())
     }
     pub fn add_user_to_wager(
         &mut self,
@@ -256,89 +783,484 @@ impl Room {
         outcome_id: ID,
         amount: i64,
     ) -> Result<i64, StateMutationError> {
+        let checkpoint = self.snapshot([user_id], [], [wager_id]);
+        let wager = self
+            .wagers
+            .get_mut(&wager_id)
+            .ok_or(RoomMutationError::NonexistentWager {
+                wager_id,
+                room_code: self.room_code,
+            })?;
+        wager.ensure_open()?;
         let user = self
             .members
             .get_mut(&user_id)
             .ok_or(RoomMutationError::UserNotInRoom(user_id, self.room_code))?;
-        if (user.score() - amount < 0) {
+        if user.available(self.config.score_floor()) < amount {
             return Err(RoomMutationError::NegativeScore.into());
         }
-        self.wagers
+        wager.join(user_id, outcome_id, amount)?;
+        user.set_score(user.score() - amount, self.config.score_floor())
+            .expect("Already asserted earlier in function");
+        user.current_wagers_mut().insert(wager_id);
+        let new_score = user.score();
+        self.push_checkpoint(checkpoint);
+        self.ledger.record(
+            LedgerAction::WagerStake { wager_id },
+            Some(user_id),
+            user_id,
+            -amount,
+        );
+        Ok(new_score)
+    }
+    //Returns how much the stake moved by and the member's new score, nothing is recorded if it didn't
+    pub fn change_bet(
+        &mut self,
+        wager_id: ID,
+        user_id: ID,
+        outcome_id: ID,
+        amount: i64,
+    ) -> Result<(i64, i64), StateMutationError> {
+        let checkpoint = self.snapshot([user_id], [], [wager_id]);
+        let wager = self
+            .wagers
             .get_mut(&wager_id)
             .ok_or(RoomMutationError::NonexistentWager {
                 wager_id,
                 room_code: self.room_code,
-            })?
-            .join(user_id, outcome_id, amount)?;
-        user.set_score(user.score() - amount)
+            })?;
+        wager.ensure_open()?;
+        let old = wager
+            .bet(user_id, outcome_id)
+            .ok_or(WagerMutationError::BetNotFound {
+                user_id,
+                outcome_id,
+            })?;
+        let user = self
+            .members
+            .get_mut(&user_id)
+            .ok_or(RoomMutationError::UserNotInRoom(user_id, self.room_code))?;
+        if amount == old {
+            return Ok((0, user.score()));
+        }
+        if user.available(self.config.score_floor()) < amount - old {
+            return Err(RoomMutationError::NegativeScore.into());
+        }
+        let delta = wager.change_bet(user_id, outcome_id, amount)?;
+        user.set_score(user.score() - delta, self.config.score_floor())
             .expect("Already asserted earlier in function");
-        user.current_wagers_mut().insert(wager_id);
-        Ok(user.score())
+        if !wager.has_bets(user_id) {
+            user.current_wagers_mut().remove(&wager_id);
+        }
+        let new_score = user.score();
+        self.push_checkpoint(checkpoint);
+        let action = if delta > 0 {
+            LedgerAction::WagerStake { wager_id }
+        } else {
+            LedgerAction::BetWithdrawal { wager_id }
+        };
+        self.ledger.record(action, Some(user_id), user_id, -delta);
+        Ok((delta, new_score))
     }
     pub fn resolve_wager(
         &mut self,
+        actor: Option<ID>,
         wager_id: ID,
         outcome_id: ID,
     ) -> Result<Vec<(ID, i64)>, StateMutationError> {
-        let results = self
+        let wager = self
             .wagers
-            .get_mut(&wager_id)
+            .get(&wager_id)
             .ok_or(RoomMutationError::NonexistentWager {
                 wager_id,
                 room_code: self.room_code,
-            })?
+            })?;
+        let checkpoint = self.snapshot(wager.bets().map(|(id, _)| id), [], [wager_id]);
+        let results = self
+            .wagers
+            .get_mut(&wager_id)
+            .expect("Already found the wager")
             .resolve(outcome_id)?;
-        let mut out = Vec::new();
-        if let Err(nonexistent) = results.iter().try_fold((), |_, next| {
-            self.members
-                .contains_key(&next.participant)
-                .then(|| ())
-                .ok_or(next.participant)
-        }) {
-            return Err(RoomMutationError::UserNotInRoom(nonexistent, self.room_code).into());
+        //Kept stakes can outlive their owner, whatever they'd have been paid is shared between
+        //everyone still here by how much they're paid
+        let (results, orphaned): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .partition(|result| self.members.contains_key(&result.participant));
+        let orphaned: i64 = orphaned.iter().map(|result| result.score_diff).sum();
+        if results.is_empty() && orphaned != 0 {
+            log::warn!("Nobody left in {} to pay {orphaned} to", self.room_code);
         }
-
-        for result in results {
+        let weights = results.iter().map(|result| result.score_diff).collect_vec();
+        let mut out = Vec::new();
+        for (result, share) in results.into_iter().zip(share_out(orphaned, &weights)) {
             let user = self
                 .members
                 .get_mut(&result.participant)
-                .expect("Already asserted existence of user");
-
-            user.set_score(user.score() + result.score_diff)
+                .expect("Only members are paid");
+            let score_diff = result.score_diff + share;
+            user.set_score(user.score() + score_diff, self.config.score_floor())
                 .expect("Score diff should never be negative!");
-            user.current_wagers_mut().remove(&wager_id);
             out.push((user.id(), user.score()));
+            self.ledger.record(
+                LedgerAction::WagerPayout { wager_id },
+                actor,
+                result.participant,
+                score_diff,
+            );
+        }
+        //Losers and hedgers need clearing out too, not just whoever got paid
+        for member in self.members.values_mut() {
+            member.current_wagers_mut().remove(&wager_id);
         }
         self.wagers.remove(&wager_id);
+        self.push_checkpoint(checkpoint);
+        Ok(out)
+    }
+    pub fn lock_wager(
+        &mut self,
+        wager_id: ID,
+        closes_at: Option<u64>,
+    ) -> Result<(BettingStatus, Option<u64>), RoomMutationError> {
+        let wager = self
+            .wagers
+            .get_mut(&wager_id)
+            .ok_or(RoomMutationError::NonexistentWager {
+                wager_id,
+                room_code: self.room_code,
+            })?;
+        wager.betting_mut().lock(closes_at);
+        Ok((wager.betting().status(), wager.betting().closes_at()))
+    }
+    pub fn cancel_wager(
+        &mut self,
+        actor: Option<ID>,
+        wager_id: ID,
+    ) -> Result<Vec<ScoreUpdate>, RoomMutationError> {
+        let wager = self
+            .wagers
+            .get(&wager_id)
+            .ok_or(RoomMutationError::NonexistentWager {
+                wager_id,
+                room_code: self.room_code,
+            })?;
+        let checkpoint = self.snapshot(wager.bets().map(|(id, _)| id), [], [wager_id]);
+        let wager = self
+            .wagers
+            .remove(&wager_id)
+            .expect("Already found the wager");
+        let total = wager.bets().map(|(_, bet)| bet).sum::<i64>() + wager.forfeited();
+        let refunds = wager
+            .bets()
+            .filter(|(id, _)| self.members.contains_key(id))
+            .sorted()
+            .collect_vec();
+        let out = self.refund(
+            LedgerAction::WagerRefund { wager_id },
+            actor,
+            refunds,
+            total,
+        );
+        for member in self.members.values_mut() {
+            member.current_wagers_mut().remove(&wager_id);
+        }
+        self.push_checkpoint(checkpoint);
         Ok(out)
     }
+    //Taken before a mutation with everything it might touch, turned into a checkpoint with
+    //push_checkpoint once it's succeeded
+    fn snapshot(
+        &self,
+        members: impl IntoIterator<Item = ID>,
+        pots: impl IntoIterator<Item = ID>,
+        wagers: impl IntoIterator<Item = ID>,
+    ) -> Snapshot {
+        Snapshot {
+            members: entries(&self.members, members),
+            pots: entries(&self.pots, pots),
+            wagers: entries(&self.wagers, wagers),
+        }
+    }
+    fn push_checkpoint(&mut self, before: Snapshot) {
+        if self.checkpoints.len() == MAX_UNDO_DEPTH {
+            self.checkpoints.pop_front();
+        }
+        let checkpoint = Checkpoint {
+            members: changed_entries(before.members, &self.members),
+            pots: changed_entries(before.pots, &self.pots),
+            wagers: changed_entries(before.wagers, &self.wagers),
+            departures: Vec::new(),
+        };
+        self.checkpoints.push_back(checkpoint);
+    }
+    //Rolls back up to count mutations, members who've left since aren't brought back and their
+    //stakes are settled again the same way they were when they left
+    pub fn undo(
+        &mut self,
+        actor: Option<ID>,
+        count: usize,
+    ) -> Result<UndoOutcome, RoomMutationError> {
+        if self.checkpoints.is_empty() || count == 0 {
+            return Err(RoomMutationError::NothingToUndo(self.room_code));
+        }
+        let undone = count.min(self.checkpoints.len());
+        let checkpoints = self
+            .checkpoints
+            .drain(self.checkpoints.len() - undone..)
+            .collect_vec();
+        //Departures get settled again below, so any pot or wager could end up different
+        let scores_before: HashMap<ID, i64> = self
+            .members
+            .iter()
+            .map(|(id, member)| (*id, member.score()))
+            .collect();
+        let pots_before = self.pots.clone();
+        let wagers_before = self.wagers.clone();
+
+        let departures = checkpoints
+            .iter()
+            .flat_map(|checkpoint| checkpoint.departures.iter().copied())
+            .collect_vec();
+        for checkpoint in checkpoints.into_iter().rev() {
+            for (id, restored) in checkpoint.members {
+                if let (Some(member), Some(restored)) = (self.members.get_mut(&id), restored) {
+                    //Role changes aren't checkpointed, so they aren't undone either
+                    let role = member.role();
+                    *member = restored;
+                    member.set_role(role);
+                }
+            }
+            for (id, restored) in checkpoint.pots {
+                match restored {
+                    Some(mut pot) => {
+                        //Locks aren't checkpointed, the stakes go back but any locking since stays put
+                        if let Some(live) = self.pots.get(&id) {
+                            *pot.betting_mut() = *live.betting();
+                        }
+                        self.pots.insert(id, pot)
+                    }
+                    None => self.pots.remove(&id),
+                };
+            }
+            for (id, restored) in checkpoint.wagers {
+                match restored {
+                    Some(mut wager) => {
+                        if let Some(live) = self.wagers.get(&id) {
+                            *wager.betting_mut() = *live.betting();
+                        }
+                        self.wagers.insert(id, wager)
+                    }
+                    None => self.wagers.remove(&id),
+                };
+            }
+        }
+        for (id, policy) in departures {
+            for pot in self.pots.values_mut() {
+                if pot.has_participant(id) {
+                    pot.remove_user(id, policy)
+                        .expect("Already checked they're in the pot");
+                }
+            }
+            for wager in self.wagers.values_mut() {
+                wager.remove_user(id, policy);
+            }
+        }
+        for member in self.members.values_mut() {
+            member
+                .current_pots_mut()
+                .retain(|id| self.pots.contains_key(id));
+            member
+                .current_wagers_mut()
+                .retain(|id| self.wagers.contains_key(id));
+        }
+
+        let mut outcome = UndoOutcome {
+            undone,
+            ..Default::default()
+        };
+        for (id, member) in self.members.iter().sorted_by_key(|(id, _)| **id) {
+            let diff = member.score() - scores_before[id];
+            if diff != 0 {
+                outcome.scores.push((*id, member.score()));
+                self.ledger.record(LedgerAction::Undo, actor, *id, diff);
+            }
+        }
+        outcome.restored_pots = self
+            .pots
+            .iter()
+            .filter(|(id, pot)| pots_before.get(id) != Some(pot))
+            .sorted_by_key(|(id, _)| **id)
+            .map(|(_, pot)| pot.clone())
+            .collect();
+        outcome.removed_pots = pots_before
+            .keys()
+            .filter(|id| !self.pots.contains_key(id))
+            .copied()
+            .sorted()
+            .collect();
+        outcome.restored_wagers = self
+            .wagers
+            .iter()
+            .filter(|(id, wager)| wagers_before.get(id) != Some(wager))
+            .sorted_by_key(|(id, _)| **id)
+            .map(|(_, wager)| wager.clone())
+            .collect();
+        outcome.removed_wagers = wagers_before
+            .keys()
+            .filter(|id| !self.wagers.contains_key(id))
+            .copied()
+            .sorted()
+            .collect();
+        Ok(outcome)
+    }
+    //Deadlines are enforced on join anyway, this is so everyone finds out when they pass
+    pub fn close_due_betting(&mut self, now: u64) -> (Vec<Closure>, Vec<Closure>) {
+        let pots = self
+            .pots
+            .values_mut()
+            .filter_map(|pot| {
+                pot.betting_mut()
+                    .close_if_due(now)
+                    .then(|| (pot.id(), pot.betting().closes_at()))
+            })
+            .collect();
+        let wagers = self
+            .wagers
+            .values_mut()
+            .filter_map(|wager| {
+                wager
+                    .betting_mut()
+                    .close_if_due(now)
+                    .then(|| (wager.id(), wager.betting().closes_at()))
+            })
+            .collect();
+        (pots, wagers)
+    }
+    pub fn wager_odds(&self, wager_id: ID) -> Option<HashMap<ID, usize>> {
+        self.wagers.get(&wager_id).and_then(Wager::odds)
+    }
+    pub fn config(&self) -> &RoomConfig {
+        &self.config
+    }
+    //Returns the config as it is after the patch
+    pub fn update_config(
+        &mut self,
+        patch: RoomConfigPatch,
+    ) -> Result<RoomConfig, RoomMutationError> {
+        let config = self.config.patched(patch);
+        if !config.is_valid() {
+            return Err(RoomMutationError::InvalidConfig(self.room_code));
+        }
+        self.config = config;
+        Ok(self.config.clone())
+    }
+    //Empty for longer than the ttl, or older than the max age whoever's still in it
+    pub fn is_expired(&self, ttl: Duration, max_age: Option<Duration>) -> bool {
+        self.empty_since.is_some_and(|since| since.elapsed() >= ttl)
+            || max_age.is_some_and(|age| {
+                u128::from(timestamp().saturating_sub(self.created_at)) >= age.as_millis()
+            })
+    }
     pub fn code(&self) -> RoomCode {
         self.room_code
     }
     pub fn id_lookup(&self, addr: &SocketAddr) -> Option<ID> {
         self.address_map.get(addr).copied()
     }
-    pub fn is_admin(&self, addr: &SocketAddr) -> bool {
-        self.admins.contains(addr)
+    pub fn has_permission(&self, addr: &SocketAddr, permission: Permission) -> bool {
+        self.id_lookup(addr)
+            .and_then(|id| self.members.get(&id))
+            .is_some_and(|member| member.role().can(permission))
+    }
+    pub fn history(&self, offset: usize, limit: usize) -> (Vec<LedgerEntry>, usize) {
+        (self.ledger.page(offset, limit), self.ledger.len())
     }
-    pub fn get_sync_data(&self) -> (Vec<MemberState>, Vec<Pot>, Vec<Wager>) {
+    pub fn get_sync_data(&self) -> (Vec<MemberState>, Vec<Pot>, Vec<Wager>, RoomConfig) {
         (
             self.members.clone().into_values().collect(),
             self.pots.clone().into_values().collect(),
             self.wagers.clone().into_values().collect(),
+            self.config.clone(),
         )
     }
 }
 
+//Splits amount by weight, rounding down with the leftover points handed out a point at a time
+//from the first, evenly if none of the weights are above zero
+pub fn share_out(amount: i64, weights: &[i64]) -> Vec<i64> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    let total: i64 = weights.iter().map(|weight| (*weight).max(0)).sum();
+    let mut shares = if total > 0 {
+        weights
+            .iter()
+            .map(|weight| amount * (*weight).max(0) / total)
+            .collect_vec()
+    } else {
+        vec![amount / weights.len() as i64; weights.len()]
+    };
+    let remainder = amount - shares.iter().sum::<i64>();
+    for i in 0..remainder.max(0) as usize {
+        shares[i % weights.len()] += 1;
+    }
+    shares
+}
+
+//How a draw picks its winner, kept simple so anyone can replay it from the seed and the entrants
+//sorted by id: seed a ChaCha8 generator with seed_from_u64, take one next_u64, reduce it modulo the
+//total weight, then walk the weights in order until the roll falls inside one. Weights below zero
+//count as zero, and if none are above zero every entrant counts as one. There has to be at least one
+pub fn pick_by_weight(seed: u64, weights: &[i64]) -> usize {
+    let mut weights = weights
+        .iter()
+        .map(|weight| (*weight).max(0) as u64)
+        .collect_vec();
+    if weights.iter().all(|weight| *weight == 0) {
+        weights.fill(1);
+    }
+    let mut roll = ChaCha8Rng::seed_from_u64(seed).next_u64() % weights.iter().sum::<u64>();
+    weights
+        .iter()
+        .position(|weight| {
+            if roll < *weight {
+                true
+            } else {
+                roll -= weight;
+                false
+            }
+        })
+        .expect("The roll is below the total weight")
+}
+
+//Copies of the given entries, None for ones that don't exist
+fn entries<V: Clone>(
+    map: &HashMap<ID, V>,
+    ids: impl IntoIterator<Item = ID>,
+) -> HashMap<ID, Option<V>> {
+    ids.into_iter()
+        .map(|id| (id, map.get(&id).cloned()))
+        .collect()
+}
+
+//Drops every entry of before that's still the same now
+fn changed_entries<V: PartialEq>(
+    before: HashMap<ID, Option<V>>,
+    after: &HashMap<ID, V>,
+) -> HashMap<ID, Option<V>> {
+    before
+        .into_iter()
+        .filter(|(id, value)| after.get(id) != value.as_ref())
+        .collect()
+}
+
 impl Debug for Room {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Room: \n   Room Code: {:?}\n     Members: {:?}\n   Address Map: {:?}\n     Admins: {:?}\n    Pots: {:?}\n    Wagers: {:?}\n    Next Member Id: {:?} \n     Next Pot Id: {:?} \n    Next Wager Id : {:?}",
+            "Room: \n   Room Code: {:?}\n     Members: {:?}\n   Address Map: {:?}\n    Pots: {:?}\n    Wagers: {:?}\n    Next Member Id: {:?} \n     Next Pot Id: {:?} \n    Next Wager Id : {:?}",
             self.room_code,
             self.members,
             self.address_map,
-            self.admins,
             self.pots,
             self.wagers,
             self.next_member_id,
@@ -351,7 +1273,6 @@ impl Debug for Room {
         //     .field("members", &self.members)
         //     .field("address_map", &self.address_map)
         //     .field("next_member_id", &self.next_member_id)
-        //     .field("admins", &self.admins)
         //     .field("pots", &self.pots)
         //     .field("next_pot_id", &self.next_pot_id)
         //     .field("wagers", &self.wagers)
@@ -367,6 +1288,13 @@ impl From<[char; 8]> for RoomCode {
         RoomCode(value)
     }
 }
+impl RoomCode {
+    pub fn generate() -> Self {
+        RoomCode(std::array::from_fn(|_| {
+            char::from(ROOM_CODE_ALPHABET[OsRng.gen_range(0..ROOM_CODE_ALPHABET.len())])
+        }))
+    }
+}
 
 impl Debug for RoomCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
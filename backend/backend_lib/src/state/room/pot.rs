@@ -1,31 +1,78 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use serde::Serialize;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
-use crate::state::{ID, error::PotMutationError};
+use crate::state::{
+    ID,
+    error::PotMutationError,
+    room::{Betting, StakePolicy},
+};
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
+//How a pot is shared out when it has more than one winner
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, schemars::JsonSchema)]
+#[serde(tag = "kind")]
+pub enum PotSplit {
+    #[default]
+    Equal,
+    //One percentage per winner, in the same order as the winners
+    Percentages {
+        percentages: Vec<u8>,
+    },
+    //Each winner's share is in proportion to how much they put in
+    Proportional,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
 pub struct Pot {
     pot_id: ID,
     total_score: i64,
     score_requirement: i64,
     participants: HashSet<ID>,
     description: String,
+    #[serde(flatten)]
+    betting: Betting,
+    //Participants choose how much to put in, with score_requirement as the minimum
+    #[serde(default)]
+    variable_buy_in: bool,
+    #[serde(default)]
+    contributions: HashMap<ID, i64>,
 }
 
 impl Pot {
-    pub fn new(id: ID, score_requirement: i64, desc: String) -> Pot {
+    pub fn new(id: ID, score_requirement: i64, desc: String, variable_buy_in: bool) -> Pot {
         Pot {
             pot_id: id,
             total_score: 0,
             score_requirement,
             participants: HashSet::new(),
             description: desc,
+            betting: Betting::default(),
+            variable_buy_in,
+            contributions: HashMap::new(),
         }
     }
-    pub fn join(&mut self, id: ID) -> Result<(), PotMutationError> {
+    //What joining costs, fixed pots always take the requirement whatever was asked for
+    pub fn buy_in(&self, amount: Option<i64>) -> Result<i64, PotMutationError> {
+        match amount {
+            Some(amount) if self.variable_buy_in => {
+                if amount < self.score_requirement {
+                    Err(PotMutationError::BelowMinimum {
+                        pot_id: self.pot_id,
+                        amount,
+                        minimum: self.score_requirement,
+                    })
+                } else {
+                    Ok(amount)
+                }
+            }
+            _ => Ok(self.score_requirement),
+        }
+    }
+    pub fn join(&mut self, id: ID, amount: i64) -> Result<(), PotMutationError> {
         if self.participants.insert(id) {
-            self.total_score += self.score_requirement;
+            self.total_score += amount;
+            self.contributions.insert(id, amount);
             Ok(())
         } else {
             Err(PotMutationError::UserAlreadyExists {
@@ -34,22 +81,105 @@ impl Pot {
             })
         }
     }
-    pub fn remove_user(&mut self, id: ID) -> Result<(), PotMutationError> {
-        if self.participants.remove(&id) {
-            Ok(())
-        } else {
-            Err(PotMutationError::UserNotInPot {
+    //Returns whether the pot changed, kept stakes leave the departed member in place
+    pub fn remove_user(&mut self, id: ID, policy: StakePolicy) -> Result<bool, PotMutationError> {
+        if !self.participants.contains(&id) {
+            return Err(PotMutationError::UserNotInPot {
                 user_id: id,
                 pot_id: self.pot_id,
-            })
+            });
+        }
+        match policy {
+            StakePolicy::Keep => Ok(false),
+            StakePolicy::Forfeit => {
+                self.contributions.remove(&id);
+                Ok(self.participants.remove(&id))
+            }
+            StakePolicy::Void => {
+                self.total_score -= self.contribution(id);
+                self.participants.remove(&id);
+                self.contributions.remove(&id);
+                Ok(true)
+            }
+        }
+    }
+    pub fn ensure_open(&self) -> Result<(), PotMutationError> {
+        if self.betting.is_open() {
+            Ok(())
+        } else {
+            Err(PotMutationError::PotLocked(self.pot_id))
         }
     }
-    pub fn score_req(&self) -> i64 {
-        self.score_requirement
+    pub fn betting(&self) -> &Betting {
+        &self.betting
+    }
+    pub fn betting_mut(&mut self) -> &mut Betting {
+        &mut self.betting
+    }
+    pub fn id(&self) -> ID {
+        self.pot_id
+    }
+    //Pots saved before contributions were tracked only ever took the requirement
+    pub fn contribution(&self, id: ID) -> i64 {
+        self.contributions
+            .get(&id)
+            .copied()
+            .unwrap_or(self.score_requirement)
     }
     pub fn resolve(&self) -> i64 {
         self.total_score
     }
+    //Shares are rounded down, then whatever's left over goes a point at a time from the first winner
+    pub fn payouts(
+        &self,
+        winners: &[ID],
+        split: &PotSplit,
+    ) -> Result<Vec<(ID, i64)>, PotMutationError> {
+        let invalid = PotMutationError::InvalidSplit(self.pot_id);
+        if winners.is_empty() || winners.iter().duplicates().next().is_some() {
+            return Err(invalid);
+        }
+        let mut shares = match split {
+            PotSplit::Equal => vec![self.total_score / winners.len() as i64; winners.len()],
+            PotSplit::Percentages { percentages } => {
+                if percentages.len() != winners.len()
+                    || percentages.iter().map(|p| u32::from(*p)).sum::<u32>() != 100
+                {
+                    return Err(invalid);
+                }
+                percentages
+                    .iter()
+                    .map(|p| self.total_score * i64::from(*p) / 100)
+                    .collect()
+            }
+            PotSplit::Proportional => {
+                let contributed: i64 = winners.iter().map(|id| self.contribution(*id)).sum();
+                if winners.iter().any(|id| !self.has_participant(*id)) || contributed <= 0 {
+                    return Err(invalid);
+                }
+                winners
+                    .iter()
+                    .map(|id| self.total_score * self.contribution(*id) / contributed)
+                    .collect()
+            }
+        };
+        let remainder = self.total_score - shares.iter().sum::<i64>();
+        for i in 0..remainder.max(0) as usize {
+            shares[i % winners.len()] += 1;
+        }
+        Ok(winners.iter().copied().zip(shares).collect())
+    }
+    //What each participant has put in, sorted so a draw can be replayed from its seed
+    pub fn stakes(&self) -> Vec<(ID, i64)> {
+        self.participants
+            .iter()
+            .sorted()
+            .map(|id| (*id, self.contribution(*id)))
+            .collect()
+    }
+    pub fn has_participant(&self, id: ID) -> bool {
+        self.participants.contains(&id)
+    }
     pub fn participants(&self) -> impl Iterator<Item = &ID> {
         self.participants.iter()
     }
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, schemars::JsonSchema,
+)]
+pub enum Role {
+    //Whoever has the admin password, can do anything
+    Owner,
+    //Runs pots and wagers and keeps members in line
+    Moderator,
+    //Hands out and takes away score
+    Banker,
+    #[default]
+    Player,
+    //Can look but not take part
+    Spectator,
+}
+
+//What a message needs its sender to be allowed to do in the room it targets
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Permission {
+    //Deleting the room, changing its config and undoing
+    ManageRoom,
+    ManageRoles,
+    RemoveMembers,
+    //Blessing, removing and transferring other members' score
+    ManageScores,
+    //Creating, locking, resolving and cancelling pots and wagers
+    ManageStakes,
+    //Giving score and putting it into pots and wagers
+    Play,
+}
+
+impl Role {
+    pub fn can(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::ManageRoom | Permission::ManageRoles => matches!(self, Role::Owner),
+            Permission::RemoveMembers | Permission::ManageStakes => {
+                matches!(self, Role::Owner | Role::Moderator)
+            }
+            Permission::ManageScores => matches!(self, Role::Owner | Role::Banker),
+            Permission::Play => !matches!(self, Role::Spectator),
+        }
+    }
+}
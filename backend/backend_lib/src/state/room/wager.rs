@@ -1,91 +1,270 @@
-use std::{
-    collections::{HashMap, HashSet},
-    iter::zip,
-};
+use std::collections::HashMap;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{ID, error::WagerMutationError};
+use crate::state::{
+    ID,
+    error::WagerMutationError,
+    room::{Betting, StakePolicy, share_out},
+};
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, schemars::JsonSchema,
+)]
+#[serde(tag = "kind")]
+pub enum WagerKind {
+    //Winners are paid from nowhere according to their outcome's odds
+    #[default]
+    FixedOdds,
+    //Every stake goes into one pool which the winners split by how much they bet
+    Pool {
+        house_cut_percent: u8,
+    },
+    //Like fixed odds, but every bet reprices the outcomes from how the stakes are spread
+    DynamicOdds,
+}
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, schemars::JsonSchema)]
 pub struct Wager {
     id: ID,
-    participant_bets: HashMap<ID, i64>,
-    //Keys are the choices, values is who chose
-    participant_choices: HashMap<ID, HashSet<ID>>,
+    //Member id to the amount they've put on each outcome
+    participant_bets: HashMap<ID, HashMap<ID, i64>>,
     outcomes: HashMap<ID, WagerOutcome>,
     name: String,
+    #[serde(default)]
+    kind: WagerKind,
+    //The odds each bet was placed at, so repricing never moves an existing bet
+    #[serde(default)]
+    participant_odds: HashMap<ID, HashMap<ID, usize>>,
+    //Stakes left behind by departed members, shared between the winners
+    #[serde(default)]
+    forfeited: i64,
+    #[serde(flatten)]
+    betting: Betting,
 }
 impl Wager {
-    pub fn new(id: ID, name: String, outcomes: Vec<WagerOutcome>) -> Self {
+    pub fn new(id: ID, name: String, outcomes: Vec<WagerOutcome>, kind: WagerKind) -> Self {
         Self {
             id,
             name,
+            kind,
             outcomes: outcomes
                 .into_iter()
                 .map(|outcome| (outcome.id, outcome))
                 .collect(),
             participant_bets: HashMap::new(),
-            participant_choices: HashMap::new(),
+            participant_odds: HashMap::new(),
+            forfeited: 0,
+            betting: Betting::default(),
         }
     }
+    //Betting again on the same outcome raises the existing bet
     pub fn join(&mut self, user: ID, outcome: ID, amount: i64) -> Result<(), WagerMutationError> {
-        if self.participant_bets.contains_key(&user) {
-            Err(WagerMutationError::UserAlreadyExists {
-                user_id: user,
+        let odds = self
+            .outcomes
+            .get(&outcome)
+            .ok_or(WagerMutationError::NonexistentOutcome {
+                outcome_id: outcome,
                 wager_id: self.id,
-            })
-        } else {
-            if let Some(choice) = self.participant_choices.get_mut(&outcome) {
-                if !choice.insert(user) {
-                    //This like might be unreachable?
-                    return Err(WagerMutationError::UserAlreadyChose {
-                        user_id: user,
-                        outcome_id: outcome,
-                    });
+            })?
+            .odds;
+        let amount = amount.abs();
+        let bet = self
+            .participant_bets
+            .entry(user)
+            .or_default()
+            .entry(outcome)
+            .or_default();
+        let placed_odds = self
+            .participant_odds
+            .entry(user)
+            .or_default()
+            .entry(outcome)
+            .or_insert(odds);
+        //A raise gets the current price on the extra stake only
+        if *bet + amount > 0 {
+            *placed_odds =
+                ((*placed_odds as i64 * *bet + odds as i64 * amount) / (*bet + amount)) as usize;
+        }
+        *bet += amount;
+        self.reprice();
+        Ok(())
+    }
+    //Sets a bet to a new amount, zero withdraws it entirely, returns how much the stake moved by
+    pub fn change_bet(
+        &mut self,
+        user: ID,
+        outcome: ID,
+        amount: i64,
+    ) -> Result<i64, WagerMutationError> {
+        let old = self
+            .bet(user, outcome)
+            .ok_or(WagerMutationError::BetNotFound {
+                user_id: user,
+                outcome_id: outcome,
+            })?;
+        let delta = amount - old;
+        if delta > 0 {
+            return self.join(user, outcome, delta).map(|_| delta);
+        }
+        let bets = self
+            .participant_bets
+            .get_mut(&user)
+            .expect("Already found the bet");
+        if amount == 0 {
+            bets.remove(&outcome);
+            if bets.is_empty() {
+                self.participant_bets.remove(&user);
+            }
+            if let Some(odds) = self.participant_odds.get_mut(&user) {
+                odds.remove(&outcome);
+                if odds.is_empty() {
+                    self.participant_odds.remove(&user);
                 }
-            } else {
-                self.participant_choices
-                    .insert(outcome, HashSet::from([user]));
             }
-            self.participant_bets.insert(user, amount.abs());
+        } else {
+            bets.insert(outcome, amount);
+        }
+        self.reprice();
+        Ok(delta)
+    }
+    pub fn bet(&self, user: ID, outcome: ID) -> Option<i64> {
+        self.participant_bets.get(&user)?.get(&outcome).copied()
+    }
+    pub fn has_bets(&self, user: ID) -> bool {
+        self.participant_bets.contains_key(&user)
+    }
+    pub fn ensure_open(&self) -> Result<(), WagerMutationError> {
+        if self.betting.is_open() {
             Ok(())
+        } else {
+            Err(WagerMutationError::WagerLocked(self.id))
+        }
+    }
+    pub fn betting(&self) -> &Betting {
+        &self.betting
+    }
+    pub fn betting_mut(&mut self) -> &mut Betting {
+        &mut self.betting
+    }
+    pub fn id(&self) -> ID {
+        self.id
+    }
+    //Returns whether the wager changed
+    pub fn remove_user(&mut self, user: ID, policy: StakePolicy) -> bool {
+        if policy == StakePolicy::Keep {
+            return false;
         }
+        let Some(bets) = self.participant_bets.remove(&user) else {
+            return false;
+        };
+        self.participant_odds.remove(&user);
+        if policy == StakePolicy::Forfeit {
+            self.forfeited += bets.values().sum::<i64>();
+        }
+        self.reprice();
+        true
     }
-    pub fn remove_user(&mut self, user: ID) {
-        self.participant_bets.remove(&user);
-        for choice_set in self.participant_choices.values_mut() {
-            choice_set.remove(&user);
+    //Fair odds from the current stakes, outcomes nobody has backed keep their last price
+    fn reprice(&mut self) {
+        if self.kind != WagerKind::DynamicOdds {
+            return;
+        }
+        let total = self.pool();
+        for (id, outcome) in self.outcomes.iter_mut() {
+            let staked = Self::staked_on(&self.participant_bets, *id);
+            if staked > 0 {
+                outcome.odds = ((total - staked) * 100 / staked) as usize;
+            }
         }
     }
+    //Current price of every outcome, only interesting when the wager reprices itself
+    pub fn odds(&self) -> Option<HashMap<ID, usize>> {
+        (self.kind == WagerKind::DynamicOdds).then(|| {
+            self.outcomes
+                .iter()
+                .map(|(id, outcome)| (*id, outcome.odds))
+                .collect()
+        })
+    }
+    fn pool(&self) -> i64 {
+        self.participant_bets
+            .values()
+            .flat_map(|bets| bets.values())
+            .sum()
+    }
+    fn staked_on(participant_bets: &HashMap<ID, HashMap<ID, i64>>, outcome: ID) -> i64 {
+        participant_bets
+            .values()
+            .filter_map(|bets| bets.get(&outcome))
+            .sum()
+    }
+    //Whatever's left of every stake once the house takes its cut, rounded down
+    fn pool_after_cut(&self, house_cut_percent: u8) -> i64 {
+        (self.pool() + self.forfeited) * (100 - i64::from(house_cut_percent)) / 100
+    }
+    pub fn forfeited(&self) -> i64 {
+        self.forfeited
+    }
+    //Everything each member has staked across all outcomes
+    pub fn bets(&self) -> impl Iterator<Item = (ID, i64)> {
+        self.participant_bets
+            .iter()
+            .map(|(id, bets)| (*id, bets.values().sum()))
+    }
     pub fn resolve(&mut self, outcome: ID) -> Result<Vec<WagerResult>, WagerMutationError> {
-        let score_mult: f128 = (self
+        let outcome_odds = self
             .outcomes
             .get(&outcome)
             .ok_or(WagerMutationError::NonexistentOutcome {
                 outcome_id: outcome,
                 wager_id: self.id,
             })?
-            .odds) as f128
-            / 100.0;
-        let winner_ids = if let Some(ids) = self.participant_choices.get(&outcome) {
-            ids
-        } else {
+            .odds;
+        let winning_total = Self::staked_on(&self.participant_bets, outcome);
+        if winning_total == 0 {
+            if matches!(self.kind, WagerKind::Pool { .. }) {
+                //Nobody to split the pool between, so everyone gets their stake back along with a
+                //share of the forfeited stakes by how much they bet
+                let bets = self.bets().sorted().collect_vec();
+                let weights = bets.iter().map(|(_, bet)| *bet).collect_vec();
+                return Ok(bets
+                    .into_iter()
+                    .zip(share_out(self.forfeited, &weights))
+                    .map(|((participant, bet), share)| WagerResult {
+                        participant,
+                        score_diff: bet + share,
+                    })
+                    .collect());
+            }
             log::warn!("No winners in bet!");
             return Ok(Vec::new());
-        };
+        }
+        //Only bets on the winning outcome pay out, anything else the member staked is lost
         Ok(self
             .participant_bets
             .iter()
-            .filter_map(|(id, bet)| {
-                if winner_ids.contains(id) {
-                    let score_diff: i64 = (((*bet as f128) * score_mult).round() as i64) + bet;
-                    Some(WagerResult {
-                        participant: *id,
-                        score_diff,
-                    })
-                } else {
-                    None
+            .filter_map(|(id, bets)| Some((id, *bets.get(&outcome)?)))
+            .map(|(id, bet)| {
+                let score_diff: i64 = match self.kind {
+                    WagerKind::FixedOdds | WagerKind::DynamicOdds => {
+                        let odds = self
+                            .participant_odds
+                            .get(id)
+                            .and_then(|odds| odds.get(&outcome))
+                            .unwrap_or(&outcome_odds);
+                        let score_mult = (*odds as f128) / 100.0;
+                        let forfeit_share = self.forfeited * bet / winning_total;
+                        (((bet as f128) * score_mult).round() as i64) + bet + forfeit_share
+                    }
+                    WagerKind::Pool { house_cut_percent } => {
+                        self.pool_after_cut(house_cut_percent) * bet / winning_total
+                    }
+                };
+                WagerResult {
+                    participant: *id,
+                    score_diff,
                 }
             })
             .collect())
@@ -97,8 +276,6 @@ impl Wager {
         self.name.clone()
     }
     pub fn outcomes(&self) -> Vec<WagerOutcome> {
-        use itertools::Itertools;
-
         self.outcomes
             .clone()
             .into_iter()
@@ -1,4 +1,12 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use error::MessageHandleError;
 use itertools::Itertools;
@@ -7,66 +15,291 @@ use parking_lot::RwLock;
 use crate::connection::WSMessage;
 use crate::connection::error::MessageSendError;
 use crate::connection::message::{ClientMessage, Destination, ServerMessage};
-use crate::state::error::RoomMutationError;
+use crate::state::error::{RoomMutationError, StorageError};
+use crate::state::storage::RoomStorage;
 
-use super::room::RoomCode;
+use super::room::{
+    BettingStatus, Departure, RoomCode,
+    config::RoomConfig,
+    role::{Permission, Role},
+};
 use super::*;
 
+const MAX_HISTORY_PAGE: usize = 100;
+
 type LockedMap<K, V> = RwLock<HashMap<K, RwLock<V>>>;
 type Locked2Map<K, V1, V2> = RwLock<HashMap<K, (RwLock<V1>, RwLock<V2>)>>;
 
 pub struct ServerState {
     rooms: LockedMap<RoomCode, Room>,
     sessions: Locked2Map<SocketAddr, SessionData, crate::connection::Tx>,
+    storage: Option<Arc<dyn RoomStorage>>,
+    //How many rooms the idle sweeper has removed since startup
+    expired_rooms: AtomicUsize,
+}
+
+pub struct RoomSnapshot {
+    storage: Arc<dyn RoomStorage>,
+    data: Vec<u8>,
+}
+impl RoomSnapshot {
+    pub fn write(self) -> Result<(), StorageError> {
+        self.storage.write(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoomCounts {
+    pub active: usize,
+    pub expired: usize,
 }
 impl ServerState {
     pub fn new() -> Arc<RwLock<ServerState>> {
         let state = Self {
             rooms: HashMap::new().into(),
             sessions: HashMap::new().into(),
+            storage: None,
+            expired_rooms: AtomicUsize::new(0),
         };
         Arc::new(RwLock::new(state))
     }
+    pub fn with_storage(
+        storage: impl RoomStorage + 'static,
+    ) -> Result<Arc<RwLock<ServerState>>, StorageError> {
+        let rooms = storage
+            .load()?
+            .into_iter()
+            .map(|mut room| {
+                room.disconnect_unbound_users();
+                (room.code(), RwLock::new(room))
+            })
+            .collect::<HashMap<_, _>>();
+        log::info!("Loaded {} rooms from storage", rooms.len());
+        let state = Self {
+            rooms: rooms.into(),
+            sessions: HashMap::new().into(),
+            storage: Some(Arc::new(storage)),
+            expired_rooms: AtomicUsize::new(0),
+        };
+        Ok(Arc::new(RwLock::new(state)))
+    }
+    //Only encodes the rooms, writing it out is left to RoomSnapshot::write once the locks are gone
+    pub fn snapshot_rooms(&self) -> Result<Option<RoomSnapshot>, StorageError> {
+        let Some(storage) = &self.storage else {
+            return Ok(None);
+        };
+        let rooms = self.rooms.read();
+        let rooms = rooms.values().map(|room| room.read()).collect_vec();
+        let data = storage.encode(&rooms.iter().map(|room| &**room).collect_vec())?;
+        Ok(Some(RoomSnapshot {
+            storage: storage.clone(),
+            data,
+        }))
+    }
+    pub fn save_rooms(&self) -> Result<(), StorageError> {
+        match self.snapshot_rooms()? {
+            Some(snapshot) => snapshot.write(),
+            None => Ok(()),
+        }
+    }
 
-    pub fn add_room(&self, code: RoomCode, admin_pass: String) -> Result<(), MessageHandleError> {
+    pub fn add_room(
+        &self,
+        code: RoomCode,
+        admin_pass: String,
+        config: RoomConfig,
+    ) -> Result<(), MessageHandleError> {
         if self.rooms.read().contains_key(&code) {
             Err(MessageHandleError::RoomAlreadyExists(code))
+        } else if !config.is_valid() {
+            Err(RoomMutationError::InvalidConfig(code).into())
         } else {
             self.rooms
                 .write()
-                .insert(code, RwLock::new(Room::new(code, &admin_pass)));
+                .insert(code, RwLock::new(Room::new(code, &admin_pass, config)));
 
             Ok(())
         }
     }
-    pub fn delete_room(&self, code: RoomCode) -> Result<(), MessageHandleError> {
-        self.rooms
+    //Holds the write lock throughout so nobody can take the code between checking and inserting
+    pub fn add_generated_room(
+        &self,
+        admin_pass: String,
+        config: RoomConfig,
+    ) -> Result<RoomCode, MessageHandleError> {
+        let mut rooms = self.rooms.write();
+        let code = std::iter::repeat_with(RoomCode::generate)
+            .find(|code| !rooms.contains_key(code))
+            .expect("Codes are generated forever");
+        if !config.is_valid() {
+            return Err(RoomMutationError::InvalidConfig(code).into());
+        }
+        rooms.insert(code, RwLock::new(Room::new(code, &admin_pass, config)));
+        Ok(code)
+    }
+    //Hands back everyone who was in the room so only they hear about it
+    pub fn delete_room(&self, code: RoomCode) -> Result<Vec<SocketAddr>, MessageHandleError> {
+        let room = self
+            .rooms
             .write()
             .remove(&code)
-            .map(|_| ())
-            .ok_or(MessageHandleError::NonexistentRoom(code))
+            .ok_or(MessageHandleError::NonexistentRoom(code))?
+            .into_inner();
+        for (session, _) in self.sessions.read().values() {
+            session.write().leave_room(&code);
+        }
+        Ok(room.get_addresses().copied().sorted().collect_vec())
     }
     pub fn add_user_to_room(
         &self,
         code: RoomCode,
         user: SocketAddr,
         name: String,
+    ) -> Result<(ID, String), MessageHandleError> {
+        match self.rooms.read().get(&code) {
+            Some(room) => {
+                let (id, token) = room.write().add_user(user, name)?;
+                self.join_session_room(&user, code)?;
+                Ok((id, token))
+            }
+            None => Err(MessageHandleError::NonexistentRoom(code)),
+        }
+    }
+    pub fn rejoin_room(
+        &self,
+        code: RoomCode,
+        user: SocketAddr,
+        token: &str,
     ) -> Result<ID, MessageHandleError> {
         match self.rooms.read().get(&code) {
             Some(room) => {
-                let id = room.write().add_user(user, name)?;
-                self.sessions
-                    .read()
-                    .get(&user)
-                    .ok_or(MessageHandleError::NonexistentSession(user))?
-                    .0
-                    .write()
-                    .set_current_room(code);
+                let id = room.write().rejoin_user(user, token)?;
+                self.join_session_room(&user, code)?;
                 Ok(id)
             }
             None => Err(MessageHandleError::NonexistentRoom(code)),
         }
     }
+    fn join_session_room(
+        &self,
+        user: &SocketAddr,
+        code: RoomCode,
+    ) -> Result<(), MessageHandleError> {
+        self.sessions
+            .read()
+            .get(user)
+            .ok_or(MessageHandleError::NonexistentSession(*user))?
+            .0
+            .write()
+            .join_room(code);
+        Ok(())
+    }
+    //Removed members may have no session left, so there's nothing to fail on
+    fn leave_session_room(&self, user: &SocketAddr, code: &RoomCode) {
+        if let Some((session, _)) = self.sessions.read().get(user) {
+            session.write().leave_room(code);
+        }
+    }
+    pub fn expire_disconnected_users(&self, grace_period: Duration) {
+        //send_to_room takes the rooms lock again, so it has to be released before sending
+        let departures = self
+            .rooms
+            .read()
+            .iter()
+            .flat_map(|(code, room)| {
+                room.write()
+                    .expire_disconnected_users(grace_period)
+                    .into_iter()
+                    .map(|departure| (*code, departure))
+            })
+            .collect_vec();
+        for (code, departure) in departures {
+            let removed = ServerMessage::UserRemoved {
+                room_code: code,
+                id: departure.id,
+            };
+            for msg in std::iter::once(removed).chain(stake_updates(code, departure)) {
+                if let Err(es) = self.send_to_room(&code, msg) {
+                    es.iter().for_each(|e| log::error!("{e}"));
+                }
+            }
+        }
+    }
+    //Returns how many rooms were removed. Checking and removing happen under the same write lock
+    //so nobody can join a room between it being picked and it going
+    pub fn expire_idle_rooms(&self, ttl: Duration, max_age: Option<Duration>) -> usize {
+        let expired = {
+            let mut rooms = self.rooms.write();
+            let codes = rooms
+                .iter()
+                .filter(|(_, room)| room.read().is_expired(ttl, max_age))
+                .map(|(code, _)| *code)
+                .collect_vec();
+            codes
+                .into_iter()
+                .map(|code| {
+                    let room = rooms.remove(&code).expect("Just found it").into_inner();
+                    (code, room.get_addresses().copied().collect_vec())
+                })
+                .collect_vec()
+        };
+        for (code, addrs) in &expired {
+            log::trace!("Expiring idle room {code}");
+            for (session, _) in self.sessions.read().values() {
+                session.write().leave_room(code);
+            }
+            for addr in addrs {
+                if let Err(e) =
+                    self.send_to_addr(addr, ServerMessage::RoomDeleted { room_code: *code })
+                {
+                    log::error!("{e}");
+                }
+            }
+        }
+        self.expired_rooms
+            .fetch_add(expired.len(), Ordering::Relaxed);
+        expired.len()
+    }
+    pub fn room_counts(&self) -> RoomCounts {
+        RoomCounts {
+            active: self.rooms.read().len(),
+            expired: self.expired_rooms.load(Ordering::Relaxed),
+        }
+    }
+    //Takes the time to check against so callers aren't at the mercy of the clock
+    pub fn close_due_betting(&self, now: u64) {
+        //Closures are collected first so the rooms lock is released before send_to_room retakes it
+        let closures = self
+            .rooms
+            .read()
+            .iter()
+            .map(|(code, room)| (*code, room.write().close_due_betting(now)))
+            .collect_vec();
+        for (code, (pots, wagers)) in closures {
+            let pots =
+                pots.into_iter()
+                    .map(|(pot_id, closes_at)| ServerMessage::PotStatusChanged {
+                        room_code: code,
+                        pot_id,
+                        status: BettingStatus::Locked,
+                        closes_at,
+                    });
+            let wagers =
+                wagers
+                    .into_iter()
+                    .map(|(wager_id, closes_at)| ServerMessage::WagerStatusChanged {
+                        room_code: code,
+                        wager_id,
+                        status: BettingStatus::Locked,
+                        closes_at,
+                    });
+            for msg in pots.chain(wagers) {
+                if let Err(es) = self.send_to_room(&code, msg) {
+                    es.iter().for_each(|e| log::error!("{e}"));
+                }
+            }
+        }
+    }
 
     pub fn init_session(&self, addr: SocketAddr, tx: crate::connection::Tx) {
         let session_data = RwLock::new(SessionData::new());
@@ -78,13 +311,11 @@ impl ServerState {
         log::trace!("starting cleanup for addr:{addr}");
         if let Some((sess, _)) = self.sessions.write().remove(addr) {
             let rooms = self.rooms.read();
-            if let Some(room) = sess.read().current_room().and_then(|room| rooms.get(&room)) {
-                let mut room = room.upgradable_read();
-                if let Some(id) = room.id_lookup(addr) {
-                    log::trace!("Removing user: {id} from room {}", room.code());
-                    let _ = room
-                        .with_upgraded(|room| room.remove_user(id))
-                        .inspect_err(|e| log::error!("{e}"));
+            for room in sess.read().rooms().filter_map(|room| rooms.get(room)) {
+                let mut room = room.write();
+                room.remove_watcher(addr);
+                if let Some(id) = room.disconnect_user(addr) {
+                    log::trace!("Disconnected user: {id} from room {}", room.code());
                 }
             }
         }
@@ -109,20 +340,18 @@ impl ServerState {
     ) -> Result<(), MessageSendError> {
         self.send_ws_message(addr, msg.try_into().map_err(Into::into)?)
     }
+    //Sessions can be in several rooms, so the room has to come from the message being replied to
     pub fn send_to_peers(
         &self,
         user: &SocketAddr,
+        room: Option<RoomCode>,
         msg: impl TryInto<WSMessage, Error = impl Into<MessageSendError>>,
         inclusive: bool,
     ) -> Result<(), Vec<MessageSendError>> {
-        let sessions = self.sessions.read();
-        let (user_sess_data, _) = sessions
-            .get(user)
-            .ok_or(vec![MessageSendError::NonexistentSession(*user)])?;
-        let user_room = user_sess_data
-            .read()
-            .current_room()
-            .ok_or(vec![MessageSendError::UserNotInRoom])?;
+        if !self.sessions.read().contains_key(user) {
+            return Err(vec![MessageSendError::NonexistentSession(*user)]);
+        }
+        let user_room = room.ok_or(vec![MessageSendError::UserNotInRoom])?;
         let peers = self
             .rooms
             .read()
@@ -151,6 +380,33 @@ impl ServerState {
             Err(errors)
         }
     }
+    pub fn send_to_room(
+        &self,
+        code: &RoomCode,
+        msg: impl TryInto<WSMessage, Error = impl Into<MessageSendError>>,
+    ) -> Result<(), Vec<MessageSendError>> {
+        let peers = self
+            .rooms
+            .read()
+            .get(code)
+            .ok_or(vec![MessageSendError::NonexistentRoom(*code)])?
+            .read()
+            .get_addresses()
+            .copied()
+            .collect_vec();
+        let message: WSMessage = msg.try_into().map_err(|x| vec![x.into()])?;
+        let mut errors = Vec::new();
+        for peer in peers {
+            if let Err(e) = self.send_ws_message(&peer, message.clone()) {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
     pub fn send_to_everyone(
         &self,
         msg: impl TryInto<WSMessage, Error = impl Into<MessageSendError>>,
@@ -176,16 +432,27 @@ impl ServerState {
         sender: SocketAddr,
     ) -> Result<Vec<(ServerMessage, Destination)>, MessageHandleError> {
         match message {
-            ClientMessage::CreateRoom { code, admin_pass } => {
-                self.add_room(code, admin_pass)?;
+            ClientMessage::CreateRoom {
+                code,
+                admin_pass,
+                config,
+            } => {
+                self.add_room(code, admin_pass, config)?;
                 Ok(Vec::new())
             }
+            ClientMessage::CreateGeneratedRoom { admin_pass, config } => {
+                let code = self.add_generated_room(admin_pass, config)?;
+                Ok(vec![(
+                    ServerMessage::RoomCreated { code },
+                    Destination::Myself,
+                )])
+            }
             ClientMessage::JoinRoom {
                 code: room_code,
                 name,
             } => {
-                let id = self.add_user_to_room(room_code, sender, name.clone())?;
-                let (members, pots, wager) = self
+                let (id, member_token) = self.add_user_to_room(room_code, sender, name.clone())?;
+                let (members, pots, wager, config) = self
                     .rooms
                     .read()
                     .get(&room_code)
@@ -194,84 +461,242 @@ impl ServerState {
                     .get_sync_data();
                 Ok(vec![
                     (
-                        ServerMessage::UserJoined { name, id },
+                        ServerMessage::UserJoined {
+                            room_code,
+                            name,
+                            id,
+                        },
                         Destination::PeersInclusive,
                     ),
                     (
                         ServerMessage::SynchronizeRoom {
+                            room_code,
                             members,
                             pots,
                             wager,
-                            requester_id: id,
+                            requester_id: Some(id),
+                            member_token: Some(member_token),
+                            config,
                         },
                         Destination::Myself,
                     ),
                 ])
             }
+            ClientMessage::Rejoin {
+                code: room_code,
+                token,
+            } => {
+                let id = self.rejoin_room(room_code, sender, &token)?;
+                let (members, pots, wager, config) = self
+                    .rooms
+                    .read()
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .read()
+                    .get_sync_data();
+                Ok(vec![(
+                    ServerMessage::SynchronizeRoom {
+                        room_code,
+                        members,
+                        pots,
+                        wager,
+                        requester_id: Some(id),
+                        member_token: Some(token),
+                        config,
+                    },
+                    Destination::Myself,
+                )])
+            }
+            ClientMessage::WatchRoom { code: room_code } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                room.add_watcher(sender)?;
+                self.join_session_room(&sender, room_code)?;
+                let (members, pots, wager, config) = room.get_sync_data();
+                Ok(vec![(
+                    ServerMessage::SynchronizeRoom {
+                        room_code,
+                        members,
+                        pots,
+                        wager,
+                        requester_id: None,
+                        member_token: None,
+                        config,
+                    },
+                    Destination::Myself,
+                )])
+            }
             ClientMessage::LeaveRoom { room_code } => {
                 let rooms = self.rooms.read();
                 let mut room = rooms
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write();
+                if room.remove_watcher(&sender) {
+                    self.leave_session_room(&sender, &room_code);
+                    return Ok(vec![(
+                        ServerMessage::RecieverLeft { room_code },
+                        Destination::Myself,
+                    )]);
+                }
                 let id = room
                     .id_lookup(&sender)
                     .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
-                room.remove_user(id)?;
-                Ok(vec![
+                let departure = room.remove_user(id)?;
+                self.leave_session_room(&sender, &room_code);
+                let mut msgs = vec![
                     (
-                        ServerMessage::UserRemoved { id },
+                        ServerMessage::UserRemoved { room_code, id },
                         Destination::PeersExclusive,
                     ),
-                    (ServerMessage::RecieverLeft, Destination::Myself),
-                ])
+                    (
+                        ServerMessage::RecieverLeft { room_code },
+                        Destination::Myself,
+                    ),
+                ];
+                msgs.extend(
+                    stake_updates(room_code, departure)
+                        .map(|msg| (msg, Destination::PeersExclusive)),
+                );
+                Ok(msgs)
             }
             ClientMessage::RemoveFromRoom {
                 code: room_code,
                 id: removed_id,
             } => {
-                let addr = self
+                let departure = self
                     .rooms
                     .read()
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write()
                     .remove_user(removed_id)?;
+                let mut msgs = vec![(
+                    ServerMessage::UserRemoved {
+                        room_code,
+                        id: removed_id,
+                    },
+                    Destination::PeersInclusive,
+                )];
+                if let Some(addr) = departure.addr {
+                    self.leave_session_room(&addr, &room_code);
+                    msgs.push((
+                        ServerMessage::RecieverLeft { room_code },
+                        Destination::Specific(addr),
+                    ));
+                }
+                msgs.extend(
+                    stake_updates(room_code, departure)
+                        .map(|msg| (msg, Destination::PeersInclusive)),
+                );
+                Ok(msgs)
+            }
+            ClientMessage::DeleteRoom { room_code } => {
+                let addrs = self.delete_room(room_code)?;
+                Ok(addrs
+                    .into_iter()
+                    .map(|addr| {
+                        (
+                            ServerMessage::RoomDeleted { room_code },
+                            Destination::Specific(addr),
+                        )
+                    })
+                    .collect_vec())
+            }
+            ClientMessage::RequestAdmin {
+                room: room_code,
+                password,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let id = room
+                    .id_lookup(&sender)
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                room.claim_owner(id, &password)?;
+
                 Ok(vec![
                     (
-                        ServerMessage::UserRemoved { id: removed_id },
+                        ServerMessage::AdminGranted { room_code },
+                        Destination::Myself,
+                    ),
+                    (
+                        ServerMessage::RoleChanged {
+                            room_code,
+                            id,
+                            role: Role::Owner,
+                        },
                         Destination::PeersInclusive,
                     ),
-                    (ServerMessage::RecieverLeft, Destination::Specific(addr)),
                 ])
             }
-            ClientMessage::DeleteRoom { room_code } => {
-                self.delete_room(room_code)?;
-                Ok(vec![(ServerMessage::RoomDeleted, Destination::Everyone)])
+            ClientMessage::GrantRole {
+                room_code,
+                id,
+                role,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                room.set_role(id, role)?;
+                Ok(vec![(
+                    ServerMessage::RoleChanged {
+                        room_code,
+                        id,
+                        role,
+                    },
+                    Destination::PeersInclusive,
+                )])
             }
-            ClientMessage::RequestAdmin { room, password } => {
+            ClientMessage::RevokeRole { room_code, id } => {
                 let rooms = self.rooms.read();
                 let mut room = rooms
-                    .get(&room)
-                    .ok_or(MessageHandleError::NonexistentRoom(room))?
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write();
-                room.id_lookup(&sender)
-                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room.code()))?;
-                room.add_admin(sender, &password)?;
-
-                Ok(vec![(ServerMessage::AdminGranted, Destination::Myself)])
+                room.set_role(id, Role::default())?;
+                Ok(vec![(
+                    ServerMessage::RoleChanged {
+                        room_code,
+                        id,
+                        role: Role::default(),
+                    },
+                    Destination::PeersInclusive,
+                )])
             }
-            ClientMessage::TransferScore { from, to, amount } => {
+            ClientMessage::TransferScore {
+                room_code,
+                from,
+                to,
+                amount,
+            } => {
+                if amount < 0 {
+                    return Err(RoomMutationError::NegativeScore.into());
+                }
                 let rooms = self.rooms.read();
-                let room_code = self.get_users_room(&sender)?;
-                let (from_info, to_info) = rooms
+                let mut room = rooms
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
-                    .write()
-                    .transfer_score(&from, &to, amount)?;
+                    .write();
+                let actor = room
+                    .id_lookup(&sender)
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                //Members can move their own score around, only bankers and owners can move anyone else's
+                if from != actor && !room.has_permission(&sender, Permission::ManageScores) {
+                    return Err(MessageHandleError::AuthorizationError);
+                }
+                let (from_info, to_info) = room.transfer_score(Some(actor), &from, &to, amount)?;
                 Ok(vec![
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id: from_info.0,
                             new_amount: from_info.1,
                         },
@@ -279,6 +704,7 @@ impl ServerState {
                     ),
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id: to_info.0,
                             new_amount: to_info.1,
                         },
@@ -286,12 +712,15 @@ impl ServerState {
                     ),
                 ])
             }
-            ClientMessage::GiveScore { to, amount } => {
+            ClientMessage::GiveScore {
+                room_code,
+                to,
+                amount,
+            } => {
                 if amount < 0 {
                     return Err(RoomMutationError::NegativeScore.into());
                 }
                 let rooms = self.rooms.read();
-                let room_code = self.get_users_room(&sender)?;
                 let mut room = rooms
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
@@ -301,10 +730,11 @@ impl ServerState {
                     .id_lookup(&sender)
                     .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
 
-                let (from_info, to_info) = room.transfer_score(&from, &to, amount)?;
+                let (from_info, to_info) = room.give_score(&from, &to, amount)?;
                 Ok(vec![
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id: from_info.0,
                             new_amount: from_info.1,
                         },
@@ -312,6 +742,7 @@ impl ServerState {
                     ),
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id: to_info.0,
                             new_amount: to_info.1,
                         },
@@ -319,32 +750,46 @@ impl ServerState {
                     ),
                 ])
             }
-            ClientMessage::BlessScore { to, amount } => {
+            ClientMessage::BlessScore {
+                room_code,
+                to,
+                amount,
+            } => {
                 let rooms = self.rooms.read();
-                let room_code = self.get_users_room(&sender)?;
                 let mut room = rooms
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write();
-                let (user_id, new_amount) = room.bless_score(&to, amount)?;
+                let actor = room
+                    .id_lookup(&sender)
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                let (user_id, new_amount) = room.bless_score(Some(actor), &to, amount)?;
                 Ok(vec![(
                     ServerMessage::ScoreChanged {
+                        room_code,
                         user_id,
                         new_amount,
                     },
                     Destination::PeersInclusive,
                 )])
             }
-            ClientMessage::RemoveScore { from, amount } => {
+            ClientMessage::RemoveScore {
+                room_code,
+                from,
+                amount,
+            } => {
                 let rooms = self.rooms.read();
-                let room_code = self.get_users_room(&sender)?;
                 let mut room = rooms
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write();
-                let (user_id, new_amount) = room.bless_score(&from, -amount)?;
+                let actor = room
+                    .id_lookup(&sender)
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                let (user_id, new_amount) = room.remove_score(Some(actor), &from, amount)?;
                 Ok(vec![(
                     ServerMessage::ScoreChanged {
+                        room_code,
                         user_id,
                         new_amount,
                     },
@@ -355,6 +800,8 @@ impl ServerState {
                 room_code,
                 score_requirement,
                 description,
+                closes_at,
+                variable_buy_in,
             } => {
                 let pot = self
                     .rooms
@@ -362,13 +809,17 @@ impl ServerState {
                     .get(&room_code)
                     .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write()
-                    .create_pot(score_requirement, description);
+                    .create_pot(score_requirement, description, closes_at, variable_buy_in)?;
                 Ok(vec![(
-                    ServerMessage::PotCreated { pot },
+                    ServerMessage::PotCreated { room_code, pot },
                     Destination::PeersInclusive,
                 )])
             }
-            ClientMessage::JoinPot { pot_id, room_code } => {
+            ClientMessage::JoinPot {
+                pot_id,
+                room_code,
+                amount,
+            } => {
                 let rooms = self.rooms.read();
                 let room = rooms
                     .get(&room_code)
@@ -377,14 +828,20 @@ impl ServerState {
                     .read()
                     .id_lookup(&sender)
                     .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
-                let new_amount = room.write().add_user_to_pot(user_id, pot_id)?;
+                let (amount, new_amount) = room.write().add_user_to_pot(user_id, pot_id, amount)?;
                 Ok(vec![
                     (
-                        ServerMessage::PotJoined { pot_id, user_id },
+                        ServerMessage::PotJoined {
+                            room_code,
+                            pot_id,
+                            user_id,
+                            amount,
+                        },
                         Destination::PeersInclusive,
                     ),
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id,
                             new_amount,
                         },
@@ -393,53 +850,145 @@ impl ServerState {
                 ])
             }
 
-            ClientMessage::ResolvePot {
+            ClientMessage::LockPot {
+                room_code,
                 pot_id,
-                winner,
-                room_id,
+                closes_at,
             } => {
-                let (winner_id, new_score) = self
+                let (status, closes_at) = self
                     .rooms
                     .read()
-                    .get(&room_id)
-                    .ok_or(MessageHandleError::NonexistentRoom(room_id))?
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write()
-                    .resolve_pot(pot_id, winner)?;
-                Ok(vec![
-                    (
-                        ServerMessage::PotResolved { id: pot_id },
-                        Destination::PeersInclusive,
-                    ),
-                    (
-                        ServerMessage::ScoreChanged {
-                            user_id: winner_id,
-                            new_amount: new_score,
-                        },
-                        Destination::PeersInclusive,
-                    ),
-                ])
+                    .lock_pot(pot_id, closes_at)?;
+                Ok(vec![(
+                    ServerMessage::PotStatusChanged {
+                        room_code,
+                        pot_id,
+                        status,
+                        closes_at,
+                    },
+                    Destination::PeersInclusive,
+                )])
+            }
+            ClientMessage::ResolvePot {
+                pot_id,
+                winners,
+                split,
+                allow_outsiders,
+                room_id: room_code,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let scores = room.resolve_pot(actor, pot_id, &winners, &split, allow_outsiders)?;
+                Ok(std::iter::once(ServerMessage::PotResolved {
+                    room_code,
+                    id: pot_id,
+                })
+                .chain(scores.into_iter().map(|(user_id, new_amount)| {
+                    ServerMessage::ScoreChanged {
+                        room_code,
+                        user_id,
+                        new_amount,
+                    }
+                }))
+                .map(|msg| (msg, Destination::PeersInclusive))
+                .collect())
+            }
+            ClientMessage::DrawPot {
+                room_code,
+                pot_id,
+                weighted,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let draw = room.draw_pot(actor, pot_id, weighted)?;
+                Ok([
+                    ServerMessage::PotDrawn {
+                        room_code,
+                        pot_id,
+                        seed: draw.seed,
+                        weighted,
+                        winner: draw.winner,
+                    },
+                    ServerMessage::PotResolved {
+                        room_code,
+                        id: pot_id,
+                    },
+                ]
+                .into_iter()
+                .chain(draw.scores.into_iter().map(|(user_id, new_amount)| {
+                    ServerMessage::ScoreChanged {
+                        room_code,
+                        user_id,
+                        new_amount,
+                    }
+                }))
+                .map(|msg| (msg, Destination::PeersInclusive))
+                .collect())
+            }
+            ClientMessage::CancelPot { room_code, pot_id } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let mut msgs = room
+                    .cancel_pot(actor, pot_id)?
+                    .into_iter()
+                    .map(|(user_id, new_amount)| {
+                        (
+                            ServerMessage::ScoreChanged {
+                                room_code,
+                                user_id,
+                                new_amount,
+                            },
+                            Destination::PeersInclusive,
+                        )
+                    })
+                    .collect_vec();
+                msgs.push((
+                    ServerMessage::PotCancelled {
+                        room_code,
+                        id: pot_id,
+                    },
+                    Destination::PeersInclusive,
+                ));
+                Ok(msgs)
             }
             ClientMessage::CreateWager {
                 name,
                 outcomes,
-                room_id,
+                room_id: room_code,
+                wager_kind,
+                closes_at,
             } => {
                 let wager = self
                     .rooms
                     .read()
-                    .get(&room_id)
-                    .ok_or(MessageHandleError::NonexistentRoom(room_id))?
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write()
-                    .create_wager(name, outcomes);
+                    .create_wager(name, outcomes, wager_kind, closes_at)?;
                 Ok(vec![(
-                    ServerMessage::WagerCreated { wager },
+                    ServerMessage::WagerCreated { room_code, wager },
                     Destination::PeersInclusive,
                 )])
             }
             ClientMessage::JoinWager {
                 wager_id,
                 outcome_id,
-                room_id,
+                room_id: room_code,
                 amount,
             } => {
                 if amount < 0 {
@@ -447,18 +996,18 @@ impl ServerState {
                 }
                 let rooms = self.rooms.read();
                 let room = rooms
-                    .get(&room_id)
-                    .ok_or(MessageHandleError::NonexistentRoom(room_id))?;
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?;
                 let user_id = room
                     .read()
                     .id_lookup(&sender)
-                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_id))?;
-                let new_score = room
-                    .write()
-                    .add_user_to_wager(wager_id, user_id, outcome_id, amount)?;
-                Ok(vec![
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                let mut room = room.write();
+                let new_score = room.add_user_to_wager(wager_id, user_id, outcome_id, amount)?;
+                let mut msgs = vec![
                     (
                         ServerMessage::WagerJoined {
+                            room_code,
                             wager_id,
                             user_id,
                             outcome_id,
@@ -468,29 +1017,114 @@ impl ServerState {
                     ),
                     (
                         ServerMessage::ScoreChanged {
+                            room_code,
                             user_id,
                             new_amount: new_score,
                         },
                         Destination::PeersInclusive,
                     ),
-                ])
+                ];
+                if let Some(odds) = room.wager_odds(wager_id) {
+                    msgs.push((
+                        ServerMessage::WagerOddsChanged {
+                            room_code,
+                            wager_id,
+                            odds,
+                        },
+                        Destination::PeersInclusive,
+                    ));
+                }
+                Ok(msgs)
             }
-            ClientMessage::ResolveWager {
+            ClientMessage::ChangeBet {
+                room_code,
+                wager_id,
+                outcome_id,
+                amount,
+            } => {
+                if amount < 0 {
+                    return Err(RoomMutationError::NegativeScore.into());
+                }
+                self.change_bet(sender, room_code, wager_id, outcome_id, amount)
+            }
+            ClientMessage::WithdrawBet {
+                room_code,
                 wager_id,
                 outcome_id,
-                room_id,
+            } => self.change_bet(sender, room_code, wager_id, outcome_id, 0),
+            ClientMessage::LockWager {
+                room_code,
+                wager_id,
+                closes_at,
             } => {
-                let mut msgs = self
+                let (status, closes_at) = self
                     .rooms
                     .read()
-                    .get(&room_id)
-                    .ok_or(MessageHandleError::NonexistentRoom(room_id))?
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
                     .write()
-                    .resolve_wager(wager_id, outcome_id)?
+                    .lock_wager(wager_id, closes_at)?;
+                Ok(vec![(
+                    ServerMessage::WagerStatusChanged {
+                        room_code,
+                        wager_id,
+                        status,
+                        closes_at,
+                    },
+                    Destination::PeersInclusive,
+                )])
+            }
+            ClientMessage::ResolveWager {
+                wager_id,
+                outcome_id,
+                room_id: room_code,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let mut msgs = room
+                    .resolve_wager(actor, wager_id, outcome_id)?
+                    .into_iter()
+                    .map(|(user_id, new_amount)| {
+                        (
+                            ServerMessage::ScoreChanged {
+                                room_code,
+                                user_id,
+                                new_amount,
+                            },
+                            Destination::PeersInclusive,
+                        )
+                    })
+                    .collect_vec();
+                msgs.push((
+                    ServerMessage::WagerResolved {
+                        room_code,
+                        id: wager_id,
+                    },
+                    Destination::PeersInclusive,
+                ));
+                Ok(msgs)
+            }
+            ClientMessage::CancelWager {
+                room_code,
+                wager_id,
+            } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let mut msgs = room
+                    .cancel_wager(actor, wager_id)?
                     .into_iter()
                     .map(|(user_id, new_amount)| {
                         (
                             ServerMessage::ScoreChanged {
+                                room_code,
                                 user_id,
                                 new_amount,
                             },
@@ -499,52 +1133,183 @@ impl ServerState {
                     })
                     .collect_vec();
                 msgs.push((
-                    ServerMessage::WagerResolved { id: wager_id },
+                    ServerMessage::WagerCancelled {
+                        room_code,
+                        id: wager_id,
+                    },
                     Destination::PeersInclusive,
                 ));
                 Ok(msgs)
             }
+            ClientMessage::RequestHistory {
+                room_code,
+                offset,
+                limit,
+            } => {
+                let rooms = self.rooms.read();
+                let room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .read();
+                room.id_lookup(&sender)
+                    .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+                let (entries, total) = room.history(offset, limit.min(MAX_HISTORY_PAGE));
+                Ok(vec![(
+                    ServerMessage::History {
+                        room_code,
+                        entries,
+                        offset,
+                        total,
+                    },
+                    Destination::Myself,
+                )])
+            }
+            ClientMessage::Undo { room_code, count } => {
+                let rooms = self.rooms.read();
+                let mut room = rooms
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write();
+                let actor = room.id_lookup(&sender);
+                let outcome = room.undo(actor, count)?;
+                let scores = outcome.scores.into_iter().map(|(user_id, new_amount)| {
+                    ServerMessage::ScoreChanged {
+                        room_code,
+                        user_id,
+                        new_amount,
+                    }
+                });
+                let pots = outcome
+                    .restored_pots
+                    .into_iter()
+                    .map(|pot| ServerMessage::PotCreated { room_code, pot })
+                    .chain(
+                        outcome
+                            .removed_pots
+                            .into_iter()
+                            .map(|pot_id| ServerMessage::PotRemoved { room_code, pot_id }),
+                    );
+                let wagers = outcome
+                    .restored_wagers
+                    .into_iter()
+                    .map(|wager| ServerMessage::WagerCreated { room_code, wager })
+                    .chain(outcome.removed_wagers.into_iter().map(|wager_id| {
+                        ServerMessage::WagerRemoved {
+                            room_code,
+                            wager_id,
+                        }
+                    }));
+                Ok(pots
+                    .chain(wagers)
+                    .chain(scores)
+                    .chain([ServerMessage::ActionsUndone {
+                        room_code,
+                        count: outcome.undone,
+                    }])
+                    .map(|msg| (msg, Destination::PeersInclusive))
+                    .collect())
+            }
+            ClientMessage::UpdateRoomConfig { room_code, changes } => {
+                let config = self
+                    .rooms
+                    .read()
+                    .get(&room_code)
+                    .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+                    .write()
+                    .update_config(changes)?;
+                Ok(vec![(
+                    ServerMessage::RoomConfigChanged { room_code, config },
+                    Destination::PeersInclusive,
+                )])
+            }
             ClientMessage::Debug => {
                 log::debug!("{self:?}");
                 Ok(Vec::new())
             }
         }
     }
-    fn get_users_room(&self, user: &SocketAddr) -> Result<RoomCode, MessageHandleError> {
-        self.sessions
-            .read()
-            .get(user)
-            .ok_or(MessageHandleError::NonexistentSession(*user))?
-            .0
-            .read()
-            .current_room()
-            .ok_or(MessageHandleError::UserNotInAnyRoom(*user))
+    fn change_bet(
+        &self,
+        sender: SocketAddr,
+        room_code: RoomCode,
+        wager_id: ID,
+        outcome_id: ID,
+        amount: i64,
+    ) -> Result<Vec<(ServerMessage, Destination)>, MessageHandleError> {
+        let rooms = self.rooms.read();
+        let mut room = rooms
+            .get(&room_code)
+            .ok_or(MessageHandleError::NonexistentRoom(room_code))?
+            .write();
+        let user_id = room
+            .id_lookup(&sender)
+            .ok_or(RoomMutationError::AddressNotInRoom(sender, room_code))?;
+        let (delta, new_score) = room.change_bet(wager_id, user_id, outcome_id, amount)?;
+        if delta == 0 {
+            return Ok(Vec::new());
+        }
+        let mut msgs = vec![
+            (
+                ServerMessage::BetChanged {
+                    room_code,
+                    wager_id,
+                    user_id,
+                    outcome_id,
+                    delta,
+                },
+                Destination::PeersInclusive,
+            ),
+            (
+                ServerMessage::ScoreChanged {
+                    room_code,
+                    user_id,
+                    new_amount: new_score,
+                },
+                Destination::PeersInclusive,
+            ),
+        ];
+        if let Some(odds) = room.wager_odds(wager_id) {
+            msgs.push((
+                ServerMessage::WagerOddsChanged {
+                    room_code,
+                    wager_id,
+                    odds,
+                },
+                Destination::PeersInclusive,
+            ));
+        }
+        Ok(msgs)
     }
-    pub fn is_user_admin(
+    pub fn user_has_permission(
         &self,
         user: &SocketAddr,
-        room_code: Option<RoomCode>,
+        room_code: RoomCode,
+        permission: Permission,
     ) -> Result<bool, MessageHandleError> {
-        let room_code = room_code.unwrap_or(
-            self.sessions
-                .read()
-                .get(user)
-                .ok_or(MessageHandleError::NonexistentSession(*user))?
-                .0
-                .read()
-                .current_room()
-                .ok_or(MessageHandleError::UserNotInAnyRoom(*user))?,
-        );
         Ok(self
             .rooms
             .read()
             .get(&room_code)
             .ok_or(MessageHandleError::NonexistentRoom(room_code))?
             .read()
-            .is_admin(user))
+            .has_permission(user, permission))
     }
 }
 
+//Lets the rest of the room know how a departure touched its pots and wagers
+fn stake_updates(room_code: RoomCode, departure: Departure) -> impl Iterator<Item = ServerMessage> {
+    departure
+        .pots
+        .into_iter()
+        .map(move |pot| ServerMessage::PotUpdated { room_code, pot })
+        .chain(
+            departure
+                .wagers
+                .into_iter()
+                .map(move |wager| ServerMessage::WagerUpdated { room_code, wager }),
+        )
+}
+
 impl std::fmt::Debug for ServerState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -1,19 +1,32 @@
+use std::collections::HashSet;
+
 use super::room::RoomCode;
 
 #[derive(Debug)]
 pub struct SessionData {
-    current_room: Option<RoomCode>,
+    rooms: HashSet<RoomCode>,
+}
+
+impl Default for SessionData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SessionData {
     pub fn new() -> Self {
-        Self { current_room: None }
+        Self {
+            rooms: HashSet::new(),
+        }
     }
 
-    pub fn current_room(&self) -> Option<RoomCode> {
-        self.current_room
+    pub fn rooms(&self) -> impl Iterator<Item = &RoomCode> {
+        self.rooms.iter()
+    }
+    pub fn join_room(&mut self, code: RoomCode) {
+        self.rooms.insert(code);
     }
-    pub fn set_current_room(&mut self, code: RoomCode) {
-        self.current_room = Some(code)
+    pub fn leave_room(&mut self, code: &RoomCode) {
+        self.rooms.remove(code);
     }
 }
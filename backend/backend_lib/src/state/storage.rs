@@ -0,0 +1,58 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use crate::state::{Room, error::StorageError};
+
+//Anything that can hold on to rooms between server restarts
+pub trait RoomStorage: Send + Sync {
+    fn load(&self) -> Result<Vec<Room>, StorageError>;
+    //Runs with every room locked, so it should do no more than turn them into bytes
+    fn encode(&self, rooms: &[&Room]) -> Result<Vec<u8>, StorageError>;
+    //Runs with nothing locked, and is allowed to block
+    fn write(&self, snapshot: &[u8]) -> Result<(), StorageError>;
+}
+
+//Snapshots every room into a single json file
+#[derive(Debug)]
+pub struct JsonFileStorage {
+    path: PathBuf,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl RoomStorage for JsonFileStorage {
+    fn load(&self) -> Result<Vec<Room>, StorageError> {
+        match File::open(&self.path) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                log::info!("No snapshot at {:?}, starting fresh", self.path);
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn encode(&self, rooms: &[&Room]) -> Result<Vec<u8>, StorageError> {
+        Ok(serde_json::to_vec(rooms)?)
+    }
+
+    fn write(&self, snapshot: &[u8]) -> Result<(), StorageError> {
+        //Write then rename, so a crash mid write can't clobber the last good snapshot
+        let tmp_path = self.path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(snapshot)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::RwLock;
+
+use crate::state::{ServerState, timestamp};
+
+pub async fn snapshot_rooms(state: Arc<RwLock<ServerState>>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        //The rooms are only locked while they're encoded, the file io happens off the async workers
+        let snapshot = match state.read().snapshot_rooms() {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("Failed to snapshot rooms: {e}");
+                continue;
+            }
+        };
+        match tokio::task::spawn_blocking(move || snapshot.write()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("Failed to snapshot rooms: {e}"),
+            Err(e) => log::error!("Snapshot task failed: {e}"),
+        }
+    }
+}
+
+pub async fn expire_disconnected_users(
+    state: Arc<RwLock<ServerState>>,
+    grace_period: Duration,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        state.read().expire_disconnected_users(grace_period);
+    }
+}
+
+pub async fn close_due_betting(state: Arc<RwLock<ServerState>>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        state.read().close_due_betting(timestamp());
+    }
+}
+
+pub async fn expire_idle_rooms(
+    state: Arc<RwLock<ServerState>>,
+    ttl: Duration,
+    max_age: Option<Duration>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let state = state.read();
+        let expired = state.expire_idle_rooms(ttl, max_age);
+        let counts = state.room_counts();
+        log::info!(
+            "Expired {expired} idle rooms. Rooms active: {}, expired since startup: {}",
+            counts.active,
+            counts.expired
+        );
+    }
+}
@@ -2,19 +2,27 @@
 * Does each message sent respond with the appropriate messages?
 */
 
+use std::collections::HashMap;
+
 use rstest::{fixture, rstest};
 
 use super::*;
 use crate::{
     connection::message::{ClientMessage, Destination, ServerMessage},
+    state::server_state::RoomCounts,
     state::{
         ID,
         error::{AdminRequestError, PotMutationError, RoomMutationError, WagerMutationError},
         room::{
-            MemberState,
-            pot::Pot,
-            wager::{Wager, WagerOutcome},
+            BettingStatus, MemberState, StakePolicy,
+            config::{MAX_CREDIT_LIMIT, RoomConfig, RoomConfigPatch},
+            ledger::LedgerAction,
+            pick_by_weight,
+            pot::{Pot, PotSplit},
+            role::{Permission, Role},
+            wager::{Wager, WagerKind, WagerOutcome},
         },
+        timestamp,
     },
     tests::StateFixture,
 };
@@ -30,15 +38,17 @@ lazy_static! {
     static ref USER_ADDR_1: SocketAddr = "127.0.0.1:8080".parse().unwrap();
     static ref USER_NAME_2: String = "user2".to_owned();
     static ref USER_ADDR_2: SocketAddr = "127.0.0.1:8081".parse().unwrap();
+    static ref USER_ADDR_3: SocketAddr = "127.0.0.1:8082".parse().unwrap();
     static ref SCORE_AMT_1: i64 = 100;
     static ref SCORE_AMT_2: i64 = 1000;
     static ref WAGER_1: Wager = Wager::new(
         0,
         "wager".to_owned(),
         vec![
-            WagerOutcome::new("Outcome 1".to_owned(), "outcome 1".to_owned(), 30),
-            WagerOutcome::new("Outcome 2".to_owned(), "outcome 2".to_owned(), 70)
-        ]
+            WagerOutcome::new("Outcome 1".to_owned(), "outcome 1".to_owned(), 30, 0),
+            WagerOutcome::new("Outcome 2".to_owned(), "outcome 2".to_owned(), 70, 1)
+        ],
+        WagerKind::FixedOdds
     );
 }
 
@@ -82,10 +92,127 @@ fn room_creation_invalid_expected() -> MessageTestErrorExpected {
     vec![MessageHandleError::RoomAlreadyExists(*ROOM_CODE_1)]
 }
 #[fixture]
+fn room_configuring_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::UserJoined {
+                room_code: *ROOM_CODE_1,
+                name: USER_NAME_1.clone(),
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::SynchronizeRoom {
+                room_code: *ROOM_CODE_1,
+                members: vec![MemberState::new(USER_NAME_1.clone(), 0, *SCORE_AMT_1)],
+                pots: vec![],
+                wager: vec![],
+                requester_id: Some(0),
+                member_token: None,
+                config: RoomConfig {
+                    starting_balance: *SCORE_AMT_1,
+                    credit_limit: *SCORE_AMT_2,
+                    ..Default::default()
+                },
+            },
+            Destination::Myself,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 - *SCORE_AMT_2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::RoomConfigChanged {
+                room_code: *ROOM_CODE_1,
+                config: RoomConfig {
+                    starting_balance: *SCORE_AMT_1,
+                    credit_limit: *SCORE_AMT_2,
+                    allow_give: false,
+                    ..Default::default()
+                },
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn room_configuring_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::InvalidConfig(*ROOM_CODE_1).into(),
+        RoomMutationError::NameTaken(" USER1 ".to_owned()).into(),
+        RoomMutationError::GivingDisabled(*ROOM_CODE_1).into(),
+        RoomMutationError::TransfersDisabled(*ROOM_CODE_1).into(),
+        RoomMutationError::InvalidConfig(*ROOM_CODE_1).into(),
+    ]
+}
+#[fixture]
+fn credit_spending_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::PotJoined {
+                room_code: *ROOM_CODE_1,
+                pot_id: 0,
+                user_id: 0,
+                amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: -*SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerJoined {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+                user_id: 0,
+                outcome_id: 0,
+                amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: -*SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn credit_spending_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        RoomMutationError::NegativeScore.into(),
+        PotMutationError::InsufficientScore {
+            user_id: 0,
+            pot_id: 1,
+            user_score: -*SCORE_AMT_1,
+            score_req: *SCORE_AMT_1,
+        }
+        .into(),
+        RoomMutationError::NegativeScore.into(),
+        RoomMutationError::InvalidConfig(*ROOM_CODE_1).into(),
+        RoomMutationError::InvalidConfig(*ROOM_CODE_1).into(),
+    ]
+}
+#[fixture]
 fn room_joining_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::UserJoined {
+                room_code: *ROOM_CODE_1,
                 name: USER_NAME_1.clone(),
                 id: 0,
             },
@@ -93,9 +220,13 @@ fn room_joining_expected() -> MessageTestExpected {
         ),
         (
             ServerMessage::SynchronizeRoom {
-                members: vec![MemberState::new(USER_NAME_1.clone(), 0)],
+                room_code: *ROOM_CODE_1,
+                members: vec![MemberState::new(USER_NAME_1.clone(), 0, 0)],
                 pots: vec![],
                 wager: vec![],
+                requester_id: Some(0),
+                member_token: None,
+                config: RoomConfig::default(),
             },
             Destination::Myself,
         ),
@@ -106,13 +237,83 @@ fn room_joining_invalid_expected() -> MessageTestErrorExpected {
     vec![MessageHandleError::NonexistentRoom(*ROOM_CODE_1)]
 }
 #[fixture]
-fn leaving_expected() -> MessageTestExpected {
+fn rejoining_expected() -> MessageTestExpected {
+    let mut member = MemberState::new(USER_NAME_1.clone(), 0, 0);
+    member.set_score(*SCORE_AMT_1, 0).unwrap();
+    member.set_role(Role::Owner);
     vec![(
-        ServerMessage::UserRemoved { id: 0 },
-        Destination::PeersExclusive,
+        ServerMessage::SynchronizeRoom {
+            room_code: *ROOM_CODE_1,
+            members: vec![member],
+            pots: vec![],
+            wager: vec![],
+            requester_id: Some(0),
+            member_token: None,
+            config: RoomConfig::default(),
+        },
+        Destination::Myself,
     )]
 }
 #[fixture]
+fn watching_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::SynchronizeRoom {
+                room_code: *ROOM_CODE_1,
+                members: vec![MemberState::new(USER_NAME_1.clone(), 0, 0)],
+                pots: vec![],
+                wager: vec![],
+                requester_id: None,
+                member_token: None,
+                config: RoomConfig::default(),
+            },
+            Destination::Myself,
+        ),
+        (
+            ServerMessage::RecieverLeft {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Myself,
+        ),
+    ]
+}
+#[fixture]
+fn watching_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::UserAlreadyExists(*USER_ADDR_2, *ROOM_CODE_1).into(),
+        RoomMutationError::AddressNotInRoom(*USER_ADDR_2, *ROOM_CODE_1).into(),
+        RoomMutationError::AddressNotInRoom(*USER_ADDR_2, *ROOM_CODE_1).into(),
+        RoomMutationError::AddressNotInRoom(*USER_ADDR_2, *ROOM_CODE_1).into(),
+    ]
+}
+#[fixture]
+fn rejoining_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::InvalidToken(*ROOM_CODE_1).into(),
+        RoomMutationError::InvalidToken(*ROOM_CODE_1).into(),
+    ]
+}
+#[fixture]
+fn leaving_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::UserRemoved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersExclusive,
+        ),
+        (
+            ServerMessage::RecieverLeft {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Myself,
+        ),
+    ]
+}
+#[fixture]
 fn leaving_invalid_expected() -> MessageTestErrorExpected {
     vec![
         MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
@@ -124,10 +325,21 @@ fn leaving_invalid_expected() -> MessageTestErrorExpected {
 }
 #[fixture]
 fn removing_expected() -> MessageTestExpected {
-    vec![(
-        ServerMessage::UserRemoved { id: 1 },
-        Destination::PeersInclusive,
-    )]
+    vec![
+        (
+            ServerMessage::UserRemoved {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::RecieverLeft {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Specific(*USER_ADDR_2),
+        ),
+    ]
 }
 #[fixture]
 fn removing_invalid_expected() -> MessageTestErrorExpected {
@@ -137,8 +349,166 @@ fn removing_invalid_expected() -> MessageTestErrorExpected {
     ]
 }
 #[fixture]
+fn leaving_with_stakes_expected() -> MessageTestExpected {
+    let mut pot = Pot::new(0, *SCORE_AMT_1, "description".to_owned(), false);
+    pot.join(0, *SCORE_AMT_1).unwrap();
+    pot.join(1, *SCORE_AMT_1).unwrap();
+    pot.remove_user(1, StakePolicy::Forfeit).unwrap();
+    let mut wager = WAGER_1.clone();
+    wager.join(0, 0, *SCORE_AMT_1).unwrap();
+    wager.join(1, 1, *SCORE_AMT_1).unwrap();
+    wager.remove_user(1, StakePolicy::Forfeit);
+    vec![
+        (
+            ServerMessage::UserRemoved {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+            },
+            Destination::PeersExclusive,
+        ),
+        (
+            ServerMessage::RecieverLeft {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Myself,
+        ),
+        (
+            ServerMessage::PotUpdated {
+                room_code: *ROOM_CODE_1,
+                pot,
+            },
+            Destination::PeersExclusive,
+        ),
+        (
+            ServerMessage::WagerUpdated {
+                room_code: *ROOM_CODE_1,
+                wager,
+            },
+            Destination::PeersExclusive,
+        ),
+    ]
+}
+#[fixture]
+fn removing_with_voided_stakes_expected() -> MessageTestExpected {
+    let mut pot = Pot::new(0, *SCORE_AMT_1, "description".to_owned(), false);
+    pot.join(0, *SCORE_AMT_1).unwrap();
+    pot.join(1, *SCORE_AMT_1).unwrap();
+    pot.remove_user(1, StakePolicy::Void).unwrap();
+    let mut wager = WAGER_1.clone();
+    wager.join(0, 0, *SCORE_AMT_1).unwrap();
+    wager.join(1, 1, *SCORE_AMT_1).unwrap();
+    wager.remove_user(1, StakePolicy::Void);
+    vec![
+        (
+            ServerMessage::UserRemoved {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::RecieverLeft {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Specific(*USER_ADDR_2),
+        ),
+        (
+            ServerMessage::PotUpdated {
+                room_code: *ROOM_CODE_1,
+                pot,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerUpdated {
+                room_code: *ROOM_CODE_1,
+                wager,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn forfeited_stake_resolution_expected() -> MessageTestExpected {
+    let winnings = (((*SCORE_AMT_1 as f128) * (30 as f128 / 100.0)).round() as i64)
+        + *SCORE_AMT_1
+        + *SCORE_AMT_1;
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: winnings,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn forfeited_stake_cancellation_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotCancelled {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 4,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerCancelled {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn kept_stake_resolution_expected() -> MessageTestExpected {
+    vec![(
+        ServerMessage::WagerResolved {
+            room_code: *ROOM_CODE_1,
+            id: 0,
+        },
+        Destination::PeersInclusive,
+    )]
+}
+#[fixture]
 fn deletion_expected() -> MessageTestExpected {
-    vec![(ServerMessage::RoomDeleted, Destination::PeersInclusive)]
+    [*USER_ADDR_1, *USER_ADDR_2, *USER_ADDR_3]
+        .into_iter()
+        .map(|addr| {
+            (
+                ServerMessage::RoomDeleted {
+                    room_code: *ROOM_CODE_1,
+                },
+                Destination::Specific(addr),
+            )
+        })
+        .collect()
 }
 #[fixture]
 fn deletion_invalid_expected() -> MessageTestErrorExpected {
@@ -146,7 +516,51 @@ fn deletion_invalid_expected() -> MessageTestErrorExpected {
 }
 #[fixture]
 fn request_success_expected() -> MessageTestExpected {
-    vec![(ServerMessage::AdminGranted, Destination::Myself)]
+    vec![
+        (
+            ServerMessage::AdminGranted {
+                room_code: *ROOM_CODE_1,
+            },
+            Destination::Myself,
+        ),
+        (
+            ServerMessage::RoleChanged {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+                role: Role::Owner,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn role_granting_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::RoleChanged {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+                role: Role::Banker,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::RoleChanged {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+                role: Role::Player,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn role_granting_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
+        RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
+    ]
 }
 #[fixture]
 fn request_failure_expected() -> MessageTestErrorExpected {
@@ -161,6 +575,7 @@ fn request_failure_expected() -> MessageTestErrorExpected {
 fn blessing_expected() -> MessageTestExpected {
     vec![(
         ServerMessage::ScoreChanged {
+            room_code: *ROOM_CODE_1,
             user_id: 0,
             new_amount: *SCORE_AMT_1,
         },
@@ -170,7 +585,7 @@ fn blessing_expected() -> MessageTestExpected {
 #[fixture]
 fn blessing_invalid_expected() -> MessageTestErrorExpected {
     vec![
-        MessageHandleError::UserNotInAnyRoom(*USER_ADDR_1),
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
         RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
         RoomMutationError::NegativeScore.into(),
     ]
@@ -179,6 +594,7 @@ fn blessing_invalid_expected() -> MessageTestErrorExpected {
 fn score_removal_expected() -> MessageTestExpected {
     vec![(
         ServerMessage::ScoreChanged {
+            room_code: *ROOM_CODE_1,
             user_id: 0,
             new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
         },
@@ -188,7 +604,7 @@ fn score_removal_expected() -> MessageTestExpected {
 #[fixture]
 fn score_removal_invalid_expected() -> MessageTestErrorExpected {
     vec![
-        MessageHandleError::UserNotInAnyRoom(*USER_ADDR_1),
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
         RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
         RoomMutationError::NegativeScore.into(),
     ]
@@ -198,6 +614,7 @@ fn score_giving_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
             },
@@ -205,6 +622,7 @@ fn score_giving_expected() -> MessageTestExpected {
         ),
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 1,
                 new_amount: *SCORE_AMT_1,
             },
@@ -215,16 +633,23 @@ fn score_giving_expected() -> MessageTestExpected {
 #[fixture]
 fn score_giving_invalid_expected() -> MessageTestErrorExpected {
     vec![
-        MessageHandleError::UserNotInAnyRoom(*USER_ADDR_1),
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
         RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
         RoomMutationError::NegativeScore.into(),
     ]
 }
 #[fixture]
+fn score_wrong_room_expected() -> MessageTestErrorExpected {
+    (0..4)
+        .map(|_| RoomMutationError::AddressNotInRoom(*USER_ADDR_1, *ROOM_CODE_2).into())
+        .collect()
+}
+#[fixture]
 fn score_transferal_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
             },
@@ -232,6 +657,7 @@ fn score_transferal_expected() -> MessageTestExpected {
         ),
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 1,
                 new_amount: *SCORE_AMT_1,
             },
@@ -242,16 +668,26 @@ fn score_transferal_expected() -> MessageTestExpected {
 #[fixture]
 fn score_transferal_invalid_expected() -> MessageTestErrorExpected {
     vec![
-        MessageHandleError::UserNotInAnyRoom(*USER_ADDR_1),
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
         RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
         RoomMutationError::UserNotInRoom(2, *ROOM_CODE_1).into(),
         RoomMutationError::NegativeScore.into(),
     ]
 }
 #[fixture]
-fn wager_creation_expected() -> MessageTestExpected {
+fn score_transferal_unauthorized_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::AuthorizationError,
+        MessageHandleError::AuthorizationError,
+        MessageHandleError::AuthorizationError,
+        RoomMutationError::NegativeScore.into(),
+    ]
+}
+#[fixture]
+fn wager_creation_expected() -> MessageTestExpected {
     vec![(
         ServerMessage::WagerCreated {
+            room_code: *ROOM_CODE_1,
             wager: WAGER_1.clone(),
         },
         Destination::PeersInclusive,
@@ -259,13 +695,17 @@ fn wager_creation_expected() -> MessageTestExpected {
 }
 #[fixture]
 fn wager_creation_invalid_expected() -> MessageTestErrorExpected {
-    vec![MessageHandleError::NonexistentRoom(*ROOM_CODE_1)]
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        WagerMutationError::InvalidHouseCut(101).into(),
+    ]
 }
 #[fixture]
 fn wager_joining_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::WagerJoined {
+                room_code: *ROOM_CODE_1,
                 wager_id: 0,
                 user_id: 0,
                 outcome_id: 0,
@@ -275,6 +715,7 @@ fn wager_joining_expected() -> MessageTestExpected {
         ),
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
             },
@@ -293,8 +734,8 @@ fn wager_joining_invalid_expected() -> MessageTestErrorExpected {
         }
         .into(),
         RoomMutationError::NegativeScore.into(),
-        WagerMutationError::UserAlreadyExists {
-            user_id: 0,
+        WagerMutationError::NonexistentOutcome {
+            outcome_id: 2,
             wager_id: 0,
         }
         .into(),
@@ -309,13 +750,237 @@ fn wager_resolution_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: score_diff,
             },
             Destination::PeersInclusive,
         ),
         (
-            ServerMessage::WagerResolved { id: 0 },
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn bet_changing_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::BetChanged {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+                user_id: 0,
+                outcome_id: 0,
+                delta: *SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_2 - *SCORE_AMT_1 * 3,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn bet_withdrawal_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::BetChanged {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+                user_id: 0,
+                outcome_id: 0,
+                delta: -*SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_2,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn bet_changing_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentWager {
+            wager_id: 0,
+            room_code: *ROOM_CODE_1,
+        }
+        .into(),
+        WagerMutationError::BetNotFound {
+            user_id: 0,
+            outcome_id: 0,
+        }
+        .into(),
+        RoomMutationError::NegativeScore.into(),
+        WagerMutationError::WagerLocked(0).into(),
+    ]
+}
+#[fixture]
+fn wager_hedging_resolution_expected() -> MessageTestExpected {
+    //The losing half of the hedge is gone, the winning half pays out at its own odds
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 130 / 100,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn wager_raising_resolution_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 2 * 130 / 100,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn dynamic_wager_joining_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::WagerJoined {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+                user_id: 0,
+                outcome_id: 0,
+                amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerOddsChanged {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+                odds: HashMap::from([(0, 0), (1, 70)]),
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn dynamic_wager_resolution_expected() -> MessageTestExpected {
+    //Repricing moved outcome 0 to evens, but the bet was placed at 30%
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 130 / 100,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pool_wager_resolution_expected() -> MessageTestExpected {
+    //Both stakes, less a 10% cut, all to the only winner
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 2 * 90 / 100,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pool_wager_refund_expected() -> MessageTestExpected {
+    //Member 1 forfeited their stake by leaving, it goes back out with member 0's refund
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pool_wager_kept_stake_expected() -> MessageTestExpected {
+    //Member 1 left a winning stake behind, their share goes to member 0 so the whole pool is paid
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 3,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
             Destination::PeersInclusive,
         ),
     ]
@@ -334,34 +999,40 @@ fn wager_resolution_invalid_expected() -> MessageTestErrorExpected {
             wager_id: 0,
         }
         .into(),
-        RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
     ]
 }
 #[fixture]
 fn pot_creation_expected() -> MessageTestExpected {
     vec![(
         ServerMessage::PotCreated {
-            pot: Pot::new(0, *SCORE_AMT_1, "description".to_owned()),
+            room_code: *ROOM_CODE_1,
+            pot: Pot::new(0, *SCORE_AMT_1, "description".to_owned(), false),
         },
         Destination::PeersInclusive,
     )]
 }
 #[fixture]
 fn pot_creation_invalid_expected() -> MessageTestErrorExpected {
-    vec![MessageHandleError::NonexistentRoom(*ROOM_CODE_1)]
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NegativeScore.into(),
+    ]
 }
 #[fixture]
 fn pot_joining_expected() -> MessageTestExpected {
     vec![
         (
             ServerMessage::PotJoined {
+                room_code: *ROOM_CODE_1,
                 pot_id: 0,
                 user_id: 0,
+                amount: *SCORE_AMT_1,
             },
             Destination::PeersInclusive,
         ),
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
             },
@@ -388,11 +1059,15 @@ fn pot_joining_invalid_expected() -> MessageTestErrorExpected {
 fn pot_resolution_expected() -> MessageTestExpected {
     vec![
         (
-            ServerMessage::PotResolved { id: 0 },
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
             Destination::PeersInclusive,
         ),
         (
             ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
                 user_id: 0,
                 new_amount: *SCORE_AMT_1 * 2,
             },
@@ -408,455 +1083,2577 @@ fn pot_resolution_invalid_expected() -> MessageTestErrorExpected {
         RoomMutationError::UserNotInRoom(1, *ROOM_CODE_1).into(),
     ]
 }
-
-#[rstest]
-fn room_creation(multi_client_state: StateFixture, room_creation_expected: MessageTestExpected) {
-    let (_, mut connections) = multi_client_state;
-    connections[0].send_message(ClientMessage::CreateRoom {
-        code: *ROOM_CODE_1,
-        admin_pass: "pass".to_string(),
-    });
-    connections[0].assert_success(room_creation_expected);
-}
-#[rstest]
-fn room_creation_invalid(
-    multi_client_state: StateFixture,
-    room_creation_invalid_expected: MessageTestErrorExpected,
-) {
-    let (_, mut connections) = multi_client_state;
-    room_init(&mut connections[0]);
-    connections[0].send_message(ClientMessage::CreateRoom {
-        code: *ROOM_CODE_1,
-        admin_pass: "pass".to_owned(),
-    });
-    connections[0].assert_failure(room_creation_invalid_expected);
-}
-#[rstest]
-fn room_joining(multi_client_state: StateFixture, room_joining_expected: MessageTestExpected) {
-    let (_, mut connections) = multi_client_state;
-    room_init(&mut connections[0]);
-
-    connections[0].send_message(ClientMessage::JoinRoom {
-        code: *ROOM_CODE_1,
-        name: USER_NAME_1.clone(),
-    });
-    connections[0].assert_success(room_joining_expected);
+#[fixture]
+fn pot_split_resolution_expected() -> MessageTestExpected {
+    //202 split 70/30 is 141/60, the leftover point goes to first place
+    vec![
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: 142,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: 60,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
 }
-#[rstest]
-fn room_joining_invalid(
-    multi_client_state: StateFixture,
-    room_joining_invalid_expected: MessageTestErrorExpected,
-) {
-    let (_, mut connections) = multi_client_state;
-    connections[0].send_message(ClientMessage::JoinRoom {
-        code: *ROOM_CODE_1,
-        name: "grog".into(),
-    });
-    connections[0].assert_failure(room_joining_invalid_expected);
+#[fixture]
+fn pot_split_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        PotMutationError::InvalidSplit(0).into(),
+        PotMutationError::InvalidSplit(0).into(),
+        PotMutationError::InvalidSplit(0).into(),
+    ]
+}
+#[fixture]
+fn pot_variable_buy_in_expected() -> MessageTestExpected {
+    //500 split 300/200 by what each winner put in
+    vec![
+        (
+            ServerMessage::PotJoined {
+                room_code: *ROOM_CODE_1,
+                pot_id: 0,
+                user_id: 0,
+                amount: 300,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_2 - 300,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_2,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pot_variable_buy_in_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        PotMutationError::BelowMinimum {
+            pot_id: 0,
+            amount: 50,
+            minimum: *SCORE_AMT_1,
+        }
+        .into(),
+        PotMutationError::InsufficientScore {
+            user_id: 0,
+            pot_id: 0,
+            user_score: *SCORE_AMT_2,
+            score_req: *SCORE_AMT_2 + 1,
+        }
+        .into(),
+        PotMutationError::InvalidSplit(0).into(),
+    ]
+}
+#[fixture]
+fn pot_outsider_resolution_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::RoomConfigChanged {
+                room_code: *ROOM_CODE_1,
+                config: RoomConfig {
+                    strict_pot_winners: false,
+                    ..Default::default()
+                },
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pot_outsider_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        PotMutationError::WinnerNotParticipant {
+            user_id: 1,
+            pot_id: 0,
+        }
+        .into(),
+        PotMutationError::WinnerNotParticipant {
+            user_id: 1,
+            pot_id: 0,
+        }
+        .into(),
+    ]
+}
+#[fixture]
+fn pot_drawing_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::PotDrawn {
+                room_code: *ROOM_CODE_1,
+                pot_id: 0,
+                seed: 0,
+                weighted: true,
+                winner: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pot_drawing_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentPot(0, *ROOM_CODE_1).into(),
+        PotMutationError::NoEntrants(0).into(),
+    ]
+}
+#[fixture]
+fn pot_locking_expected() -> MessageTestExpected {
+    vec![(
+        ServerMessage::PotStatusChanged {
+            room_code: *ROOM_CODE_1,
+            pot_id: 0,
+            status: BettingStatus::Open,
+            closes_at: Some(u64::MAX),
+        },
+        Destination::PeersInclusive,
+    )]
+}
+#[fixture]
+fn pot_locking_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentPot(0, *ROOM_CODE_1).into(),
+        PotMutationError::PotLocked(0).into(),
+    ]
+}
+#[fixture]
+fn wager_locking_expected() -> MessageTestExpected {
+    vec![(
+        ServerMessage::WagerStatusChanged {
+            room_code: *ROOM_CODE_1,
+            wager_id: 0,
+            status: BettingStatus::Locked,
+            closes_at: None,
+        },
+        Destination::PeersInclusive,
+    )]
+}
+#[fixture]
+fn wager_locking_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentWager {
+            wager_id: 0,
+            room_code: *ROOM_CODE_1,
+        }
+        .into(),
+        WagerMutationError::WagerLocked(0).into(),
+    ]
+}
+#[fixture]
+fn pot_cancellation_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotCancelled {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn pot_cancellation_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentPot(0, *ROOM_CODE_1).into(),
+    ]
+}
+#[fixture]
+fn wager_cancellation_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 1,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerCancelled {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn wager_cancellation_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NonexistentWager {
+            wager_id: 0,
+            room_code: *ROOM_CODE_1,
+        }
+        .into(),
+    ]
+}
+
+#[rstest]
+fn room_creation(multi_client_state: StateFixture, room_creation_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    connections[0].send_message(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_1,
+        admin_pass: "pass".to_string(),
+        config: RoomConfig::default(),
+    });
+    connections[0].assert_success(room_creation_expected);
+}
+#[rstest]
+fn room_creation_invalid(
+    multi_client_state: StateFixture,
+    room_creation_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    connections[0].send_message(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_1,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    connections[0].assert_failure(room_creation_invalid_expected);
+}
+#[rstest]
+fn room_generation(multi_client_state: StateFixture) {
+    let (_, mut connections) = multi_client_state;
+    for _ in 0..2 {
+        connections[0].send_message(ClientMessage::CreateGeneratedRoom {
+            admin_pass: "pass".to_owned(),
+            config: RoomConfig::default(),
+        });
+    }
+    let codes = connections[0]
+        .recieved
+        .iter()
+        .map(|msg| match msg {
+            (ServerMessage::RoomCreated { code }, Destination::Myself) => String::from(code),
+            _ => panic!("Expected a room code, got: {msg:?}"),
+        })
+        .collect_vec();
+    assert_eq!(codes.len(), 2);
+    assert_ne!(codes[0], codes[1]);
+    for code in &codes {
+        assert_eq!(code.len(), 8);
+        assert!(
+            code.chars()
+                .all(|c| c.is_ascii_alphanumeric() && !"0O1I".contains(c))
+        );
+    }
+    let ServerMessage::RoomCreated { code } = connections[0].recieved[0].0 else {
+        unreachable!()
+    };
+    connections[1].send_message(ClientMessage::JoinRoom {
+        code,
+        name: USER_NAME_1.clone(),
+    });
+    assert!(
+        connections[1].errors.is_empty(),
+        "{:?}",
+        connections[1].errors
+    );
+}
+#[rstest]
+fn room_configuring(
+    multi_client_state: StateFixture,
+    room_configuring_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    connections[0].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_1,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig {
+            starting_balance: *SCORE_AMT_1,
+            credit_limit: *SCORE_AMT_2,
+            ..Default::default()
+        },
+    });
+    connections[0].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_1.clone(),
+    });
+    connections[0].take_member_token();
+    connections[0].send_message_setup(ClientMessage::RequestAdmin {
+        room: *ROOM_CODE_1,
+        password: "pass".to_owned(),
+    });
+    connections[0].send_message(ClientMessage::RemoveScore {
+        room_code: *ROOM_CODE_1,
+        from: 0,
+        amount: *SCORE_AMT_2,
+    });
+    connections[0].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            allow_give: Some(false),
+            ..Default::default()
+        },
+    });
+    connections[0].assert_success(room_configuring_expected);
+    //A null max_members lifts the limit, leaving it out keeps it
+    let patch = |json| serde_json::from_str::<RoomConfigPatch>(json).unwrap();
+    assert_eq!(patch(r#"{"max_members":null}"#).max_members, Some(None));
+    assert_eq!(patch(r#"{"max_members":4}"#).max_members, Some(Some(4)));
+    assert_eq!(patch("{}").max_members, None);
+}
+#[rstest]
+fn room_configuring_invalid(
+    multi_client_state: StateFixture,
+    room_configuring_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let invalid = RoomConfig {
+        starting_balance: -1,
+        ..Default::default()
+    };
+    let invalid_changes = RoomConfigPatch {
+        starting_balance: Some(-1),
+        ..Default::default()
+    };
+    connections[1].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: invalid_changes.clone(),
+    });
+    connections[1].send_message(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_1,
+        admin_pass: "pass".to_owned(),
+        config: invalid.clone(),
+    });
+    connections[0].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_1,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig {
+            max_members: Some(2),
+            allow_give: false,
+            allow_transfer: false,
+            unique_names: true,
+            ..Default::default()
+        },
+    });
+    room_join_setup_admin(&mut connections[0]);
+    connections[1].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: " USER1 ".to_owned(),
+    });
+    connections[1].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_2.clone(),
+    });
+    connections[2].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: "user3".to_owned(),
+    });
+    connections[1].send_message(ClientMessage::GiveScore {
+        room_code: *ROOM_CODE_1,
+        to: 0,
+        amount: 0,
+    });
+    connections[1].send_message(ClientMessage::TransferScore {
+        room_code: *ROOM_CODE_1,
+        from: 1,
+        to: 0,
+        amount: 0,
+    });
+    connections[1].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: invalid_changes,
+    });
+    connections[1].assert_failure(room_configuring_invalid_expected);
+    connections[2].assert_failure(vec![RoomMutationError::RoomFull(*ROOM_CODE_1).into()]);
+}
+#[rstest]
+fn credit_spending(
+    multi_client_state: StateFixture,
+    credit_spending_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init_with_credit(&mut connections[0], *SCORE_AMT_1 * 2);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_pot(&mut connections[0]);
+    room_setup_wager(&mut connections[0]);
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    });
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_success(credit_spending_expected);
+
+    connections[1].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_2.clone(),
+    });
+    let Some((ServerMessage::SynchronizeRoom { members, .. }, _)) = connections[1].recieved.get(1)
+    else {
+        panic!("Expected room sync, got: {:?}", connections[1].recieved);
+    };
+    let debtor = members.iter().find(|m| m.id() == 0).unwrap();
+    assert_eq!(debtor.debt(), *SCORE_AMT_1 * 2);
+}
+#[rstest]
+fn credit_spending_invalid(
+    multi_client_state: StateFixture,
+    credit_spending_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init_with_credit(&mut connections[0], *SCORE_AMT_1);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_pot(&mut connections[0]);
+    room_setup_pot(&mut connections[0]);
+    room_setup_wager(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    //The whole limit is already spent
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: 1,
+    });
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 1,
+        amount: None,
+    });
+    connections[0].send_message(ClientMessage::RemoveScore {
+        room_code: *ROOM_CODE_1,
+        from: 0,
+        amount: 1,
+    });
+    connections[0].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            credit_limit: Some(-1),
+            ..Default::default()
+        },
+    });
+    connections[0].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            credit_limit: Some(MAX_CREDIT_LIMIT + 1),
+            ..Default::default()
+        },
+    });
+    connections[0].assert_failure(credit_spending_invalid_expected);
+}
+#[rstest]
+fn room_joining(multi_client_state: StateFixture, room_joining_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+
+    connections[0].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_1.clone(),
+    });
+    assert!(!connections[0].take_member_token().is_empty());
+    connections[0].assert_success(room_joining_expected);
+}
+#[rstest]
+fn room_joining_invalid(
+    multi_client_state: StateFixture,
+    room_joining_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    connections[0].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: "grog".into(),
+    });
+    connections[0].assert_failure(room_joining_invalid_expected);
+}
+#[rstest]
+fn room_rejoining(multi_client_state: StateFixture, rejoining_expected: MessageTestExpected) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    let token = room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    state.write().cleanup_session(&USER_ADDR_1);
+    connections[1].send_message(ClientMessage::Rejoin {
+        code: *ROOM_CODE_1,
+        token: token.clone(),
+    });
+    assert_eq!(connections[1].take_member_token(), token);
+    connections[1].assert_success(rejoining_expected);
+    assert!(
+        state
+            .read()
+            .user_has_permission(&USER_ADDR_2, *ROOM_CODE_1, Permission::ManageRoom)
+            .unwrap()
+    );
+}
+#[rstest]
+fn role_granting(multi_client_state: StateFixture, role_granting_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[1].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_2.clone(),
+    });
+    connections[0].send_message(ClientMessage::GrantRole {
+        room_code: *ROOM_CODE_1,
+        id: 1,
+        role: Role::Banker,
+    });
+    connections[0].send_message(ClientMessage::RevokeRole {
+        room_code: *ROOM_CODE_1,
+        id: 1,
+    });
+    connections[0].assert_success(role_granting_expected);
+}
+#[rstest]
+fn role_granting_invalid(
+    multi_client_state: StateFixture,
+    role_granting_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let grant = ClientMessage::GrantRole {
+        room_code: *ROOM_CODE_1,
+        id: 1,
+        role: Role::Moderator,
+    };
+    connections[0].send_message(grant.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(grant);
+    connections[0].send_message(ClientMessage::RevokeRole {
+        room_code: *ROOM_CODE_1,
+        id: 1,
+    });
+    connections[0].assert_failure(role_granting_invalid_expected);
+}
+#[rstest]
+fn role_permissions(multi_client_state: StateFixture) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    for (con, name) in [
+        (1, "banker"),
+        (2, "moderator"),
+        (3, "spectator"),
+        (4, "player"),
+    ] {
+        connections[con].send_message_setup(ClientMessage::JoinRoom {
+            code: *ROOM_CODE_1,
+            name: name.to_owned(),
+        });
+    }
+    for (id, role) in [
+        (1, Role::Banker),
+        (2, Role::Moderator),
+        (3, Role::Spectator),
+    ] {
+        connections[0].send_message_setup(ClientMessage::GrantRole {
+            room_code: *ROOM_CODE_1,
+            id,
+            role,
+        });
+    }
+    //Roles aren't part of undo checkpoints, rolling back a score change leaves them alone
+    room_setup_score(&mut connections[0], [1], *SCORE_AMT_1);
+    connections[0].send_message_setup(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    });
+
+    let permissions = [
+        Permission::ManageRoom,
+        Permission::ManageRoles,
+        Permission::RemoveMembers,
+        Permission::ManageScores,
+        Permission::ManageStakes,
+        Permission::Play,
+    ];
+    let expected = [
+        [true, true, true, true, true, true],
+        [false, false, false, true, false, true],
+        [false, false, true, false, true, true],
+        [false, false, false, false, false, false],
+        [false, false, false, false, false, true],
+        //Not in the room at all
+        [false, false, false, false, false, false],
+    ];
+    let state = state.read();
+    for (con, expected) in connections.iter().zip(expected) {
+        let permitted = permissions.map(|permission| {
+            state
+                .user_has_permission(&con.addr, *ROOM_CODE_1, permission)
+                .unwrap()
+        });
+        assert_eq!(permitted, expected, "{}", con.addr);
+    }
+}
+#[rstest]
+fn room_watching(multi_client_state: StateFixture, watching_expected: MessageTestExpected) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    connections[1].send_message(ClientMessage::WatchRoom { code: *ROOM_CODE_1 });
+
+    let msg = ServerMessage::ScoreChanged {
+        room_code: *ROOM_CODE_2,
+        user_id: 0,
+        new_amount: 0,
+    };
+    state
+        .read()
+        .send_to_peers(&USER_ADDR_1, Some(*ROOM_CODE_1), msg.clone(), false)
+        .unwrap();
+    let msg = serde_json::to_value(msg).unwrap();
+    assert_eq!(connections[1].take_pushed(), vec![msg]);
+    //Watchers aren't members, so they can't do anything a member could
+    for permission in [
+        Permission::ManageRoom,
+        Permission::ManageRoles,
+        Permission::RemoveMembers,
+        Permission::ManageScores,
+        Permission::ManageStakes,
+        Permission::Play,
+    ] {
+        assert!(
+            !state
+                .read()
+                .user_has_permission(&USER_ADDR_2, *ROOM_CODE_1, permission)
+                .unwrap()
+        );
+    }
+
+    connections[1].send_message(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[1].assert_success(watching_expected);
+    state
+        .read()
+        .send_to_room(
+            &ROOM_CODE_1,
+            ServerMessage::RoomDeleted {
+                room_code: *ROOM_CODE_1,
+            },
+        )
+        .unwrap();
+    assert!(connections[1].take_pushed().is_empty());
+}
+#[rstest]
+fn room_watching_invalid(
+    multi_client_state: StateFixture,
+    watching_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    connections[1].send_message(ClientMessage::WatchRoom { code: *ROOM_CODE_1 });
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    connections[1].send_message_setup(ClientMessage::WatchRoom { code: *ROOM_CODE_1 });
+    connections[1].send_message(ClientMessage::WatchRoom { code: *ROOM_CODE_1 });
+    connections[1].send_message(ClientMessage::GiveScore {
+        room_code: *ROOM_CODE_1,
+        to: 0,
+        amount: 0,
+    });
+    connections[1].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    });
+    connections[1].send_message(ClientMessage::RequestAdmin {
+        room: *ROOM_CODE_1,
+        password: "pass".to_owned(),
+    });
+    connections[1].assert_failure(watching_invalid_expected);
+}
+#[rstest]
+fn room_rejoining_invalid(
+    multi_client_state: StateFixture,
+    rejoining_invalid_expected: MessageTestErrorExpected,
+) {
+    let (state, mut connections) = multi_client_state;
+    connections[1].send_message(ClientMessage::Rejoin {
+        code: *ROOM_CODE_1,
+        token: "token".to_owned(),
+    });
+    room_init(&mut connections[0]);
+    let token = room_join_setup(&mut connections[0]);
+    connections[1].send_message(ClientMessage::Rejoin {
+        code: *ROOM_CODE_1,
+        token: "token".to_owned(),
+    });
+    state.write().cleanup_session(&USER_ADDR_1);
+    state
+        .read()
+        .expire_disconnected_users(std::time::Duration::ZERO);
+    connections[1].send_message(ClientMessage::Rejoin {
+        code: *ROOM_CODE_1,
+        token,
+    });
+    connections[1].assert_failure(rejoining_invalid_expected);
+}
+#[rstest]
+fn room_expiry(multi_client_state: StateFixture) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    connections[0].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_2,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    connections[0].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_2,
+        name: USER_NAME_1.clone(),
+    });
+    //Only the empty room goes when there's no max age
+    let idle_ttl = std::time::Duration::ZERO;
+    assert_eq!(state.read().expire_idle_rooms(idle_ttl, None), 1);
+    assert_eq!(
+        state.read().room_counts(),
+        RoomCounts {
+            active: 1,
+            expired: 1
+        }
+    );
+    assert!(connections[0].take_pushed().is_empty());
+    connections[1].send_message(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_1,
+        name: USER_NAME_2.clone(),
+    });
+    connections[1].assert_failure(vec![MessageHandleError::NonexistentRoom(*ROOM_CODE_1)]);
+
+    let max_age = Some(std::time::Duration::ZERO);
+    assert_eq!(state.read().expire_idle_rooms(idle_ttl, max_age), 1);
+    assert_eq!(
+        connections[0].take_pushed(),
+        vec![
+            serde_json::to_value(ServerMessage::RoomDeleted {
+                room_code: *ROOM_CODE_2
+            })
+            .unwrap()
+        ]
+    );
+    assert_eq!(state.read().room_counts().active, 0);
+}
+#[rstest]
+fn multi_room_session(multi_client_state: StateFixture) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    connections[0].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_2,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    room_join_setup(&mut connections[0]);
+    for con in [0, 1] {
+        connections[con].send_message_setup(ClientMessage::JoinRoom {
+            code: *ROOM_CODE_2,
+            name: USER_NAME_2.clone(),
+        });
+    }
+    room_join_setup(&mut connections[2]);
+
+    let msg = ServerMessage::ScoreChanged {
+        room_code: *ROOM_CODE_2,
+        user_id: 0,
+        new_amount: 0,
+    };
+    state
+        .read()
+        .send_to_peers(&USER_ADDR_1, Some(*ROOM_CODE_2), msg.clone(), true)
+        .unwrap();
+    let msg = serde_json::to_value(msg).unwrap();
+    assert_eq!(connections[0].take_pushed(), vec![msg.clone()]);
+    assert_eq!(connections[1].take_pushed(), vec![msg]);
+    assert!(connections[2].take_pushed().is_empty());
+
+    //Dropping the connection leaves both rooms, not just the last one joined
+    state.write().cleanup_session(&USER_ADDR_1);
+    state
+        .read()
+        .expire_disconnected_users(std::time::Duration::ZERO);
+    let removed =
+        |room_code| serde_json::to_value(ServerMessage::UserRemoved { room_code, id: 0 }).unwrap();
+    assert_eq!(connections[1].take_pushed(), vec![removed(*ROOM_CODE_2)]);
+    assert_eq!(connections[2].take_pushed(), vec![removed(*ROOM_CODE_1)]);
+}
+#[rstest]
+fn multi_room_messages(multi_client_state: StateFixture) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    connections[0].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_2,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    room_join_setup(&mut connections[0]);
+    connections[0].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_2,
+        name: USER_NAME_1.clone(),
+    });
+    room_join_setup(&mut connections[1]);
+    connections[2].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_2,
+        name: USER_NAME_2.clone(),
+    });
+
+    //Both rooms hand out id 1, so only the room code tells the messages apart
+    for addr in [*USER_ADDR_2, *USER_ADDR_3] {
+        state.write().cleanup_session(&addr);
+    }
+    state
+        .read()
+        .expire_disconnected_users(std::time::Duration::ZERO);
+    let mut pushed = connections[0].take_pushed();
+    pushed.sort_by_key(|msg| msg["room_code"].to_string());
+    let expected = [*ROOM_CODE_1, *ROOM_CODE_2].map(|room_code| {
+        serde_json::to_value(ServerMessage::UserRemoved { room_code, id: 1 }).unwrap()
+    });
+    assert_eq!(pushed, expected);
+}
+#[rstest]
+fn room_leaving(multi_client_state: StateFixture, leaving_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    connections[0].send_message(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].assert_success(leaving_expected);
+}
+#[rstest]
+fn room_leaving_invalid(
+    multi_client_state: StateFixture,
+    leaving_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(leaving_invalid_expected);
+}
+#[rstest]
+fn room_removing(multi_client_state: StateFixture, removing_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    connections[0].send_message(ClientMessage::RemoveFromRoom {
+        code: *ROOM_CODE_1,
+        id: 1,
+    });
+    connections[0].assert_success(removing_expected);
+}
+#[rstest]
+fn room_leaving_with_stakes(
+    multi_client_state: StateFixture,
+    leaving_with_stakes_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_setup_stakes(&mut connections);
+    connections[1].send_message(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[1].assert_success(leaving_with_stakes_expected);
+}
+#[rstest]
+fn room_removing_with_voided_stakes(
+    multi_client_state: StateFixture,
+    removing_with_voided_stakes_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_setup_stakes(&mut connections);
+    connections[0].send_message_setup(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            stake_policy: Some(StakePolicy::Void),
+            ..Default::default()
+        },
+    });
+    connections[0].send_message(ClientMessage::RemoveFromRoom {
+        code: *ROOM_CODE_1,
+        id: 1,
+    });
+    connections[0].assert_success(removing_with_voided_stakes_expected);
+}
+#[rstest]
+fn forfeited_stake_resolution(
+    multi_client_state: StateFixture,
+    forfeited_stake_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_setup_stakes(&mut connections);
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+    });
+    connections[0].assert_success(forfeited_stake_resolution_expected);
+}
+#[rstest]
+fn forfeited_stake_cancellation(
+    multi_client_state: StateFixture,
+    forfeited_stake_cancellation_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_setup_stakes(&mut connections);
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    //Member 1's forfeited stakes go to whoever's refunded rather than disappearing
+    connections[0].send_message(ClientMessage::CancelPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+    });
+    connections[0].send_message(ClientMessage::CancelWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+    });
+    connections[0].assert_success(forfeited_stake_cancellation_expected);
+}
+#[rstest]
+fn kept_stake_resolution(
+    multi_client_state: StateFixture,
+    kept_stake_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_setup_stakes(&mut connections);
+    connections[0].send_message_setup(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            stake_policy: Some(StakePolicy::Keep),
+            ..Default::default()
+        },
+    });
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 1,
+    });
+    connections[0].assert_success(kept_stake_resolution_expected);
+}
+#[rstest]
+fn room_removing_invalid(
+    multi_client_state: StateFixture,
+    removing_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::RemoveFromRoom {
+        code: *ROOM_CODE_1,
+        id: 1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(removing_invalid_expected);
+}
+#[rstest]
+fn room_deletion(multi_client_state: StateFixture, deletion_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    connections[2].send_message_setup(ClientMessage::WatchRoom { code: *ROOM_CODE_1 });
+    //Someone in another room shouldn't hear about it
+    connections[3].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_2,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    connections[3].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_2,
+        name: USER_NAME_2.clone(),
+    });
+    connections[0].send_message(ClientMessage::DeleteRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].assert_success(deletion_expected);
+}
+#[rstest]
+fn room_deletion_invalid(
+    multi_client_state: StateFixture,
+    deletion_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    connections[0].send_message(ClientMessage::DeleteRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].assert_failure(deletion_invalid_expected);
+}
+#[rstest]
+fn admin_request_success(
+    multi_client_state: StateFixture,
+    request_success_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    connections[0].send_message(ClientMessage::RequestAdmin {
+        room: *ROOM_CODE_1,
+        password: "pass".to_owned(),
+    });
+    connections[0].assert_success(request_success_expected);
+}
+#[rstest]
+fn admin_request_failure(
+    multi_client_state: StateFixture,
+    request_failure_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::RequestAdmin {
+        room: *ROOM_CODE_1,
+        password: "pass".to_owned(),
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[0]);
+    connections[0].send_message(ClientMessage::RequestAdmin {
+        room: *ROOM_CODE_1,
+        password: "bad".into(),
+    });
+    connections[0].send_message_setup(msg.clone());
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(request_failure_expected);
+}
+#[rstest]
+fn score_blessing(multi_client_state: StateFixture, blessing_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(ClientMessage::BlessScore {
+        room_code: *ROOM_CODE_1,
+        to: 0,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_success(blessing_expected);
+}
+#[rstest]
+fn score_blessing_invalid(
+    multi_client_state: StateFixture,
+    blessing_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::BlessScore {
+        room_code: *ROOM_CODE_1,
+        to: 1,
+        amount: *SCORE_AMT_1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[1]);
+    connections[0].send_message(ClientMessage::BlessScore {
+        room_code: *ROOM_CODE_1,
+        to: 1,
+        amount: -(*SCORE_AMT_1),
+    });
+    connections[0].assert_failure(blessing_invalid_expected);
+}
+#[rstest]
+fn score_removal(multi_client_state: StateFixture, score_removal_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message(ClientMessage::RemoveScore {
+        room_code: *ROOM_CODE_1,
+        from: 0,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_success(score_removal_expected);
+}
+#[rstest]
+fn score_removal_invalid(
+    multi_client_state: StateFixture,
+    score_removal_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::RemoveScore {
+        room_code: *ROOM_CODE_1,
+        from: 1,
+        amount: 1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[1]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(score_removal_invalid_expected);
+}
+#[rstest]
+fn score_giving(multi_client_state: StateFixture, score_giving_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message(ClientMessage::GiveScore {
+        room_code: *ROOM_CODE_1,
+        to: 1,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_success(score_giving_expected);
+}
+#[rstest]
+fn score_giving_invalid(
+    multi_client_state: StateFixture,
+    score_giving_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::GiveScore {
+        room_code: *ROOM_CODE_1,
+        to: 1,
+        amount: *SCORE_AMT_1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[1]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(score_giving_invalid_expected);
+}
+#[rstest]
+fn score_transferal(
+    multi_client_state: StateFixture,
+    score_transferal_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message(ClientMessage::TransferScore {
+        room_code: *ROOM_CODE_1,
+        from: 0,
+        to: 1,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_success(score_transferal_expected);
+}
+#[rstest]
+fn score_transferal_invalid(
+    multi_client_state: StateFixture,
+    score_transferal_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::TransferScore {
+        room_code: *ROOM_CODE_1,
+        from: 2,
+        to: 1,
+        amount: *SCORE_AMT_1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[1]);
+    connections[0].send_message(msg.clone());
+    room_join_setup(&mut connections[2]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(score_transferal_invalid_expected);
+}
+#[rstest]
+fn score_transferal_unauthorized(
+    multi_client_state: StateFixture,
+    score_transferal_unauthorized_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_join_setup(&mut connections[2]);
+    room_setup_score(&mut connections[0], [0, 1, 2], *SCORE_AMT_2);
+    let transfer = |from, to, amount| ClientMessage::TransferScore {
+        room_code: *ROOM_CODE_1,
+        from,
+        to,
+        amount,
+    };
+    //Taking from someone else, into your own score or a third member's
+    connections[1].send_message(transfer(0, 1, *SCORE_AMT_1));
+    connections[1].send_message(transfer(2, 0, *SCORE_AMT_1));
+    connections[1].send_message(transfer(2, 2, *SCORE_AMT_1));
+    //A negative transfer would pull score out of the recipient
+    connections[1].send_message(transfer(1, 0, -*SCORE_AMT_1));
+    connections[1].assert_failure(score_transferal_unauthorized_expected);
+    //Even for admins
+    connections[0].send_message(transfer(1, 0, -*SCORE_AMT_1));
+    connections[0].assert_failure(vec![RoomMutationError::NegativeScore.into()]);
+    //Members can still transfer out of their own score
+    connections[2].send_message(transfer(2, 0, *SCORE_AMT_1));
+    connections[2].assert_success(vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 2,
+                new_amount: *SCORE_AMT_2 - *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_2 + *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]);
+}
+#[rstest]
+fn score_wrong_room(
+    multi_client_state: StateFixture,
+    score_wrong_room_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[1].send_message_setup(ClientMessage::CreateRoom {
+        code: *ROOM_CODE_2,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
+    });
+    connections[1].send_message_setup(ClientMessage::JoinRoom {
+        code: *ROOM_CODE_2,
+        name: USER_NAME_2.clone(),
+    });
+    //Being an admin of one room is no use in another
+    let room_code = *ROOM_CODE_2;
+    for msg in [
+        ClientMessage::BlessScore {
+            room_code,
+            to: 0,
+            amount: 0,
+        },
+        ClientMessage::RemoveScore {
+            room_code,
+            from: 0,
+            amount: 0,
+        },
+        ClientMessage::GiveScore {
+            room_code,
+            to: 0,
+            amount: 0,
+        },
+        ClientMessage::TransferScore {
+            room_code,
+            from: 0,
+            to: 0,
+            amount: 0,
+        },
+    ] {
+        connections[0].send_message(msg);
+    }
+    connections[0].assert_failure(score_wrong_room_expected);
+}
+
+#[rstest]
+fn pot_creation(multi_client_state: StateFixture, pot_creation_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(ClientMessage::CreatePot {
+        room_code: *ROOM_CODE_1,
+        score_requirement: *SCORE_AMT_1,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
+    });
+    connections[0].assert_success(pot_creation_expected);
+}
+#[rstest]
+fn pot_creation_invalid(
+    multi_client_state: StateFixture,
+    pot_creation_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    connections[0].send_message(ClientMessage::CreatePot {
+        room_code: *ROOM_CODE_1,
+        score_requirement: *SCORE_AMT_1,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
+    });
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(ClientMessage::CreatePot {
+        room_code: *ROOM_CODE_1,
+        score_requirement: -*SCORE_AMT_1,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
+    });
+    connections[0].assert_failure(pot_creation_invalid_expected);
+}
+#[rstest]
+fn pot_joining(multi_client_state: StateFixture, pot_joining_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    });
+    connections[0].assert_success(pot_joining_expected);
+}
+#[rstest]
+fn pot_joining_invalid(
+    multi_client_state: StateFixture,
+    pot_joining_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(pot_joining_invalid_expected);
+}
+#[rstest]
+fn pot_resolution(multi_client_state: StateFixture, pot_resolution_expected: MessageTestExpected) {
+    let (s, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    room_join_pot(&mut connections[1]);
+    println!("{s:?}");
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![0],
+        split: PotSplit::Equal,
+        allow_outsiders: false,
+    });
+    connections[0].assert_success(pot_resolution_expected);
+}
+#[rstest]
+fn pot_resolution_invalid(
+    multi_client_state: StateFixture,
+    pot_resolution_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![1],
+        split: PotSplit::Equal,
+        allow_outsiders: false,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(pot_resolution_invalid_expected);
+}
+#[rstest]
+fn pot_split_resolution(
+    multi_client_state: StateFixture,
+    pot_split_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], 101);
+    connections[0].send_message_setup(ClientMessage::CreatePot {
+        room_code: *ROOM_CODE_1,
+        score_requirement: 101,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
+    });
+    room_join_pot(&mut connections[0]);
+    room_join_pot(&mut connections[1]);
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![1, 0],
+        split: PotSplit::Percentages {
+            percentages: vec![70, 30],
+        },
+        allow_outsiders: false,
+    });
+    connections[0].assert_success(pot_split_resolution_expected);
+}
+#[rstest]
+fn pot_variable_buy_in(
+    multi_client_state: StateFixture,
+    pot_variable_buy_in_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_2);
+    room_setup_variable_pot(&mut connections[0]);
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: Some(300),
+    });
+    connections[1].send_message_setup(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: Some(200),
+    });
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![0, 1],
+        split: PotSplit::Proportional,
+        allow_outsiders: false,
+    });
+    connections[0].assert_success(pot_variable_buy_in_expected);
+}
+#[rstest]
+fn pot_variable_buy_in_invalid(
+    multi_client_state: StateFixture,
+    pot_variable_buy_in_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    room_setup_variable_pot(&mut connections[0]);
+    for amount in [50, *SCORE_AMT_2 + 1] {
+        connections[0].send_message(ClientMessage::JoinPot {
+            room_code: *ROOM_CODE_1,
+            pot_id: 0,
+            amount: Some(amount),
+        });
+    }
+    room_join_pot(&mut connections[0]);
+    //Someone who put nothing in can't take a proportional share
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![0, 1],
+        split: PotSplit::Proportional,
+        allow_outsiders: true,
+    });
+    connections[0].assert_failure(pot_variable_buy_in_invalid_expected);
 }
 #[rstest]
-fn room_leaving(multi_client_state: StateFixture, leaving_expected: MessageTestExpected) {
+fn pot_split_invalid(
+    multi_client_state: StateFixture,
+    pot_split_invalid_expected: MessageTestErrorExpected,
+) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
-    room_join_setup(&mut connections[0]);
-    connections[0].send_message(ClientMessage::LeaveRoom {
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_pot(&mut connections[0]);
+    for (winners, split) in [
+        (vec![], PotSplit::Equal),
+        (vec![0, 0], PotSplit::Equal),
+        (
+            vec![0, 1],
+            PotSplit::Percentages {
+                percentages: vec![50, 40],
+            },
+        ),
+    ] {
+        //Nobody's joined the pot, so outsiders are let through to get as far as the split
+        connections[0].send_message(ClientMessage::ResolvePot {
+            room_id: *ROOM_CODE_1,
+            pot_id: 0,
+            winners,
+            split,
+            allow_outsiders: true,
+        });
+    }
+    connections[0].assert_failure(pot_split_invalid_expected);
+}
+#[rstest]
+fn pot_outsider_resolution(
+    multi_client_state: StateFixture,
+    pot_outsider_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1 * 2);
+    room_setup_pot(&mut connections[0]);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    connections[0].send_message_setup(ClientMessage::JoinPot {
         room_code: *ROOM_CODE_1,
+        pot_id: 1,
+        amount: None,
     });
-    connections[0].assert_success(leaving_expected);
+    //Once with the override flag, once with the room no longer being strict
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![1],
+        split: PotSplit::Equal,
+        allow_outsiders: true,
+    });
+    connections[0].send_message(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            strict_pot_winners: Some(false),
+            ..Default::default()
+        },
+    });
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 1,
+        winners: vec![1],
+        split: PotSplit::Equal,
+        allow_outsiders: false,
+    });
+    connections[0].assert_success(pot_outsider_resolution_expected);
 }
 #[rstest]
-fn room_leaving_invalid(
+fn pot_outsider_invalid(
     multi_client_state: StateFixture,
-    leaving_invalid_expected: MessageTestErrorExpected,
+    pot_outsider_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::LeaveRoom {
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![1],
+        split: PotSplit::Equal,
+        allow_outsiders: false,
+    });
+    //Outsiders have no stake to be proportional to, but that's not what's wrong with the call
+    connections[0].send_message(ClientMessage::ResolvePot {
+        room_id: *ROOM_CODE_1,
+        pot_id: 0,
+        winners: vec![1],
+        split: PotSplit::Proportional,
+        allow_outsiders: false,
+    });
+    connections[0].assert_failure(pot_outsider_invalid_expected);
+}
+#[rstest]
+fn pot_drawing(multi_client_state: StateFixture, pot_drawing_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [1], *SCORE_AMT_1);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[1]);
+    connections[0].send_message(ClientMessage::DrawPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        weighted: true,
+    });
+    //The seed is random, with one entrant only the winner is predictable
+    if let Some((ServerMessage::PotDrawn { seed, .. }, _)) = connections[0].recieved.first_mut() {
+        *seed = 0;
+    }
+    connections[0].assert_success(pot_drawing_expected);
+}
+#[rstest]
+fn pot_drawing_replay(multi_client_state: StateFixture) {
+    //Pinned so a dependency bump can't quietly change who past seeds picked
+    assert_eq!(
+        [0, 1, 42].map(|seed| pick_by_weight(seed, &[100, 300, 0, 600])),
+        [3, 1, 3]
+    );
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_join_setup(&mut connections[2]);
+    room_setup_score(&mut connections[0], [0, 1, 2], *SCORE_AMT_2);
+    room_setup_variable_pot(&mut connections[0]);
+    for (con, amount) in connections.iter_mut().zip([100, 300, 600]) {
+        con.send_message_setup(ClientMessage::JoinPot {
+            room_code: *ROOM_CODE_1,
+            pot_id: 0,
+            amount: Some(amount),
+        });
+    }
+    connections[0].send_message(ClientMessage::DrawPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        weighted: true,
+    });
+    let Some((ServerMessage::PotDrawn { seed, winner, .. }, _)) = connections[0].recieved.first()
+    else {
+        panic!("Expected a draw, got: {:?}", connections[0].recieved);
+    };
+    assert_eq!(pick_by_weight(*seed, &[100, 300, 600]), *winner);
+}
+#[rstest]
+fn pot_drawing_invalid(
+    multi_client_state: StateFixture,
+    pot_drawing_invalid_expected: MessageTestErrorExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::DrawPot {
         room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        weighted: false,
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
     connections[0].send_message(msg.clone());
-    connections[0].assert_failure(leaving_invalid_expected);
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(pot_drawing_invalid_expected);
 }
 #[rstest]
-fn room_removing(multi_client_state: StateFixture, removing_expected: MessageTestExpected) {
+fn pot_locking(multi_client_state: StateFixture, pot_locking_expected: MessageTestExpected) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    room_join_setup(&mut connections[1]);
-    connections[0].send_message(ClientMessage::RemoveFromRoom {
-        code: *ROOM_CODE_1,
-        id: 1,
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message(ClientMessage::LockPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        closes_at: Some(u64::MAX),
     });
-    connections[0].assert_success(removing_expected);
+    connections[0].assert_success(pot_locking_expected);
 }
 #[rstest]
-fn room_removing_invalid(
+fn pot_locking_invalid(
     multi_client_state: StateFixture,
-    removing_invalid_expected: MessageTestErrorExpected,
+    pot_locking_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::RemoveFromRoom {
-        code: *ROOM_CODE_1,
-        id: 1,
+    //A deadline that's already passed locks the pot straight away
+    let msg = ClientMessage::LockPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        closes_at: Some(0),
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
     connections[0].send_message(msg.clone());
-    connections[0].assert_failure(removing_invalid_expected);
+    room_setup_pot(&mut connections[0]);
+    connections[0].send_message_setup(msg.clone());
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    });
+    connections[0].assert_failure(pot_locking_invalid_expected);
 }
 #[rstest]
-fn room_deletion(multi_client_state: StateFixture, deletion_expected: MessageTestExpected) {
+fn wager_locking(multi_client_state: StateFixture, wager_locking_expected: MessageTestExpected) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(ClientMessage::DeleteRoom {
+    room_setup_wager(&mut connections[0]);
+    connections[0].send_message(ClientMessage::LockWager {
         room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        closes_at: None,
     });
-    connections[0].assert_success(deletion_expected);
+    connections[0].assert_success(wager_locking_expected);
 }
 #[rstest]
-fn room_deletion_invalid(
+fn wager_locking_invalid(
     multi_client_state: StateFixture,
-    deletion_invalid_expected: MessageTestErrorExpected,
+    wager_locking_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    connections[0].send_message(ClientMessage::DeleteRoom {
+    let msg = ClientMessage::LockWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        closes_at: None,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    connections[0].send_message(msg.clone());
+    room_setup_wager(&mut connections[0]);
+    connections[0].send_message_setup(msg.clone());
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_failure(wager_locking_invalid_expected);
+}
+#[rstest]
+fn betting_deadline(multi_client_state: StateFixture) {
+    let (state, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    //An hour out, so the pot can't close by itself while the test runs
+    let closes_at = timestamp() + 60 * 60 * 1000;
+    connections[0].send_message_setup(ClientMessage::CreatePot {
         room_code: *ROOM_CODE_1,
+        score_requirement: *SCORE_AMT_1,
+        description: "description".to_owned(),
+        closes_at: Some(closes_at),
+        variable_buy_in: false,
     });
-    connections[0].assert_failure(deletion_invalid_expected);
+    state.read().close_due_betting(timestamp());
+    assert!(connections[0].take_pushed().is_empty());
+    state.read().close_due_betting(closes_at);
+    let expected = ServerMessage::PotStatusChanged {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        status: BettingStatus::Locked,
+        closes_at: Some(closes_at),
+    };
+    assert_eq!(
+        connections[0].take_pushed(),
+        vec![serde_json::to_value(expected).unwrap()]
+    );
 }
 #[rstest]
-fn admin_request_success(
+fn pot_cancellation(
     multi_client_state: StateFixture,
-    request_success_expected: MessageTestExpected,
+    pot_cancellation_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
-    room_join_setup(&mut connections[0]);
-    connections[0].send_message(ClientMessage::RequestAdmin {
-        room: *ROOM_CODE_1,
-        password: "pass".to_owned(),
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    room_join_pot(&mut connections[1]);
+    connections[0].send_message(ClientMessage::CancelPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
     });
-    connections[0].assert_success(request_success_expected);
+    connections[0].assert_success(pot_cancellation_expected);
 }
 #[rstest]
-fn admin_request_failure(
+fn pot_cancellation_invalid(
     multi_client_state: StateFixture,
-    request_failure_expected: MessageTestErrorExpected,
+    pot_cancellation_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::RequestAdmin {
-        room: *ROOM_CODE_1,
-        password: "pass".to_owned(),
+    let msg = ClientMessage::CancelPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
     connections[0].send_message(msg.clone());
-    room_join_setup(&mut connections[0]);
-    connections[0].send_message(ClientMessage::RequestAdmin {
-        room: *ROOM_CODE_1,
-        password: "bad".into(),
+    connections[0].assert_failure(pot_cancellation_invalid_expected);
+}
+#[rstest]
+fn wager_creation(multi_client_state: StateFixture, wager_creation_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(ClientMessage::CreateWager {
+        room_id: *ROOM_CODE_1,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::FixedOdds,
+        closes_at: None,
     });
-    connections[0].send_message_setup(msg.clone());
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(request_failure_expected);
+    connections[0].assert_success(wager_creation_expected);
 }
 #[rstest]
-fn score_blessing(multi_client_state: StateFixture, blessing_expected: MessageTestExpected) {
+fn wager_creation_invalid(
+    multi_client_state: StateFixture,
+    wager_creation_invalid_expected: MessageTestErrorExpected,
+) {
     let (_, mut connections) = multi_client_state;
+    connections[0].send_message(ClientMessage::CreateWager {
+        room_id: *ROOM_CODE_1,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::FixedOdds,
+        closes_at: None,
+    });
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(ClientMessage::BlessScore {
-        to: 0,
+    connections[0].send_message(ClientMessage::CreateWager {
+        room_id: *ROOM_CODE_1,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::Pool {
+            house_cut_percent: 101,
+        },
+        closes_at: None,
+    });
+    connections[0].assert_failure(wager_creation_invalid_expected)
+}
+#[rstest]
+fn wager_joining(multi_client_state: StateFixture, wager_joining_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_wager(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
         amount: *SCORE_AMT_1,
     });
-    connections[0].assert_success(blessing_expected);
+    connections[0].assert_success(wager_joining_expected)
 }
+
 #[rstest]
-fn score_blessing_invalid(
+fn wager_joining_invalid(
     multi_client_state: StateFixture,
-    blessing_invalid_expected: MessageTestErrorExpected,
+    wager_joining_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::BlessScore {
-        to: 1,
+    let msg = ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
         amount: *SCORE_AMT_1,
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
+    connections[0].send_message(msg.clone());
     room_join_setup_admin(&mut connections[0]);
     connections[0].send_message(msg.clone());
+    room_setup_wager(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 2,
+        amount: *SCORE_AMT_1,
+    });
+    connections[0].assert_failure(wager_joining_invalid_expected);
+}
+#[rstest]
+fn wager_resolution(
+    multi_client_state: StateFixture,
+    wager_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
+
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
     room_join_setup(&mut connections[1]);
-    connections[0].send_message(ClientMessage::BlessScore {
-        to: 1,
-        amount: -(*SCORE_AMT_1),
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 1);
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
     });
-    connections[0].assert_failure(blessing_invalid_expected);
+    connections[0].assert_success(wager_resolution_expected);
 }
 #[rstest]
-fn score_removal(multi_client_state: StateFixture, score_removal_expected: MessageTestExpected) {
+fn wager_cancellation(
+    multi_client_state: StateFixture,
+    wager_cancellation_expected: MessageTestExpected,
+) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    connections[0].send_message(ClientMessage::RemoveScore {
-        from: 0,
-        amount: *SCORE_AMT_1,
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 1);
+    connections[0].send_message(ClientMessage::CancelWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
     });
-    connections[0].assert_success(score_removal_expected);
+    connections[0].assert_success(wager_cancellation_expected);
 }
 #[rstest]
-fn score_removal_invalid(
+fn wager_cancellation_invalid(
     multi_client_state: StateFixture,
-    score_removal_invalid_expected: MessageTestErrorExpected,
+    wager_cancellation_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::RemoveScore { from: 1, amount: 1 };
+    let msg = ClientMessage::CancelWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+    };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
     connections[0].send_message(msg.clone());
-    room_join_setup(&mut connections[1]);
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(score_removal_invalid_expected);
+    connections[0].assert_failure(wager_cancellation_invalid_expected);
 }
 #[rstest]
-fn score_giving(multi_client_state: StateFixture, score_giving_expected: MessageTestExpected) {
+fn bet_changing(multi_client_state: StateFixture, bet_changing_expected: MessageTestExpected) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
-    room_join_setup(&mut connections[0]);
-    room_join_setup(&mut connections[1]);
+    room_join_setup_admin(&mut connections[0]);
     room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    connections[0].send_message(ClientMessage::GiveScore {
-        to: 1,
-        amount: *SCORE_AMT_1,
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    connections[0].send_message(ClientMessage::ChangeBet {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: *SCORE_AMT_1 * 3,
     });
-    connections[0].assert_success(score_giving_expected);
+    connections[0].assert_success(bet_changing_expected);
 }
 #[rstest]
-fn score_giving_invalid(
-    multi_client_state: StateFixture,
-    score_giving_invalid_expected: MessageTestErrorExpected,
-) {
+fn bet_unchanged(multi_client_state: StateFixture) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::GiveScore {
-        to: 1,
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    connections[0].send_message(ClientMessage::ChangeBet {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
         amount: *SCORE_AMT_1,
+    });
+    assert!(
+        connections[0].errors.is_empty(),
+        "{:?}",
+        connections[0].errors
+    );
+    assert!(connections[0].recieved.is_empty());
+    connections[0].send_message(ClientMessage::RequestHistory {
+        room_code: *ROOM_CODE_1,
+        offset: 0,
+        limit: 10,
+    });
+    let Some((ServerMessage::History { entries, .. }, _)) = connections[0].recieved.pop() else {
+        panic!("Expected history, got: {:?}", connections[0].recieved);
     };
-    connections[0].send_message(msg.clone());
-    room_init(&mut connections[0]);
-    room_join_setup(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    room_join_setup(&mut connections[1]);
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(score_giving_invalid_expected);
+    assert!(entries.iter().all(|e| e.summary().3 != 0));
+    //No checkpoint either, so undoing once takes back the stake itself
+    connections[0].send_message(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    });
+    assert!(connections[0].recieved.contains(&(
+        ServerMessage::ScoreChanged {
+            room_code: *ROOM_CODE_1,
+            user_id: 0,
+            new_amount: *SCORE_AMT_2,
+        },
+        Destination::PeersInclusive,
+    )));
 }
 #[rstest]
-fn score_transferal(
-    multi_client_state: StateFixture,
-    score_transferal_expected: MessageTestExpected,
-) {
+fn bet_withdrawal(multi_client_state: StateFixture, bet_withdrawal_expected: MessageTestExpected) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    room_join_setup(&mut connections[1]);
     room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    connections[0].send_message(ClientMessage::TransferScore {
-        from: 0,
-        to: 1,
-        amount: *SCORE_AMT_1,
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    connections[0].send_message(ClientMessage::WithdrawBet {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
     });
-    connections[0].assert_success(score_transferal_expected);
+    connections[0].assert_success(bet_withdrawal_expected);
 }
 #[rstest]
-fn score_transferal_invalid(
+fn bet_changing_invalid(
     multi_client_state: StateFixture,
-    score_transferal_invalid_expected: MessageTestErrorExpected,
+    bet_changing_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::TransferScore {
-        from: 2,
-        to: 1,
-        amount: *SCORE_AMT_1,
+    let msg = ClientMessage::ChangeBet {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: *SCORE_AMT_2 + 1,
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
     connections[0].send_message(msg.clone());
-    room_join_setup(&mut connections[1]);
+    room_setup_wager(&mut connections[0]);
     connections[0].send_message(msg.clone());
-    room_join_setup(&mut connections[2]);
+    room_join_wager(&mut connections[0], 0);
     connections[0].send_message(msg.clone());
-    connections[0].assert_failure(score_transferal_invalid_expected);
+    connections[0].send_message_setup(ClientMessage::LockWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        closes_at: None,
+    });
+    connections[0].send_message(ClientMessage::WithdrawBet {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+    });
+    connections[0].assert_failure(bet_changing_invalid_expected);
 }
-
 #[rstest]
-fn pot_creation(multi_client_state: StateFixture, pot_creation_expected: MessageTestExpected) {
+fn wager_hedging_resolution(
+    multi_client_state: StateFixture,
+    wager_hedging_resolution_expected: MessageTestExpected,
+) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(ClientMessage::CreatePot {
-        room_code: *ROOM_CODE_1,
-        score_requirement: *SCORE_AMT_1,
-        description: "description".to_owned(),
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1 * 2);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[0], 1);
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
     });
-    connections[0].assert_success(pot_creation_expected);
+    connections[0].assert_success(wager_hedging_resolution_expected);
 }
 #[rstest]
-fn pot_creation_invalid(
+fn wager_raising_resolution(
     multi_client_state: StateFixture,
-    pot_creation_invalid_expected: MessageTestErrorExpected,
+    wager_raising_resolution_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    connections[0].send_message(ClientMessage::CreatePot {
-        room_code: *ROOM_CODE_1,
-        score_requirement: *SCORE_AMT_1,
-        description: "description".to_owned(),
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1 * 2);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[0], 0);
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
     });
-    connections[0].assert_failure(pot_creation_invalid_expected);
+    connections[0].assert_success(wager_raising_resolution_expected);
 }
 #[rstest]
-fn pot_joining(multi_client_state: StateFixture, pot_joining_expected: MessageTestExpected) {
+fn dynamic_wager_joining(
+    multi_client_state: StateFixture,
+    dynamic_wager_joining_expected: MessageTestExpected,
+) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    room_setup_pot(&mut connections[0]);
-    connections[0].send_message(ClientMessage::JoinPot {
-        room_code: *ROOM_CODE_1,
-        pot_id: 0,
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    room_setup_dynamic_wager(&mut connections[0]);
+    connections[0].send_message(ClientMessage::JoinWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+        amount: *SCORE_AMT_1,
     });
-    connections[0].assert_success(pot_joining_expected);
+    connections[0].assert_success(dynamic_wager_joining_expected);
 }
 #[rstest]
-fn pot_joining_invalid(
+fn dynamic_wager_resolution(
     multi_client_state: StateFixture,
-    pot_joining_invalid_expected: MessageTestErrorExpected,
+    dynamic_wager_resolution_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::JoinPot {
-        room_code: *ROOM_CODE_1,
-        pot_id: 0,
-    };
-    connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
-    connections[0].send_message(msg.clone());
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    room_setup_pot(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(pot_joining_invalid_expected);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    room_setup_dynamic_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 1);
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+    });
+    connections[0].assert_success(dynamic_wager_resolution_expected);
 }
 #[rstest]
-fn pot_resolution(multi_client_state: StateFixture, pot_resolution_expected: MessageTestExpected) {
-    let (s, mut connections) = multi_client_state;
+fn pool_wager_resolution(
+    multi_client_state: StateFixture,
+    pool_wager_resolution_expected: MessageTestExpected,
+) {
+    let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
     room_join_setup(&mut connections[1]);
     room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
-    room_setup_pot(&mut connections[0]);
-    room_join_pot(&mut connections[0]);
-    room_join_pot(&mut connections[1]);
-    println!("{s:?}");
-    connections[0].send_message(ClientMessage::ResolvePot {
+    connections[0].send_message_setup(ClientMessage::CreateWager {
         room_id: *ROOM_CODE_1,
-        pot_id: 0,
-        winner: 0,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::Pool {
+            house_cut_percent: 10,
+        },
+        closes_at: None,
     });
-    connections[0].assert_success(pot_resolution_expected);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 1);
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+    });
+    connections[0].assert_success(pool_wager_resolution_expected);
 }
 #[rstest]
-fn pot_resolution_invalid(
+fn pool_wager_refund(
     multi_client_state: StateFixture,
-    pot_resolution_invalid_expected: MessageTestErrorExpected,
+    pool_wager_refund_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::ResolvePot {
-        room_id: *ROOM_CODE_1,
-        pot_id: 0,
-        winner: 1,
-    };
-    connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    room_setup_pot(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(pot_resolution_invalid_expected);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1);
+    connections[0].send_message_setup(ClientMessage::CreateWager {
+        room_id: *ROOM_CODE_1,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::Pool {
+            house_cut_percent: 10,
+        },
+        closes_at: None,
+    });
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 0);
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 1,
+    });
+    connections[0].assert_success(pool_wager_refund_expected);
 }
 #[rstest]
-fn wager_creation(multi_client_state: StateFixture, wager_creation_expected: MessageTestExpected) {
+fn pool_wager_kept_stake(
+    multi_client_state: StateFixture,
+    pool_wager_kept_stake_expected: MessageTestExpected,
+) {
     let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(ClientMessage::CreateWager {
+    room_join_setup(&mut connections[1]);
+    room_join_setup(&mut connections[2]);
+    room_setup_score(&mut connections[0], [0, 1, 2], *SCORE_AMT_1);
+    connections[0].send_message_setup(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            stake_policy: Some(StakePolicy::Keep),
+            ..Default::default()
+        },
+    });
+    connections[0].send_message_setup(ClientMessage::CreateWager {
         room_id: *ROOM_CODE_1,
         name: WAGER_1.name(),
         outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::Pool {
+            house_cut_percent: 0,
+        },
+        closes_at: None,
     });
-    connections[0].assert_success(wager_creation_expected);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 0);
+    room_join_wager(&mut connections[2], 1);
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].send_message(ClientMessage::ResolveWager {
+        room_id: *ROOM_CODE_1,
+        wager_id: 0,
+        outcome_id: 0,
+    });
+    connections[0].assert_success(pool_wager_kept_stake_expected);
+    connections[0].send_message(ClientMessage::RequestHistory {
+        room_code: *ROOM_CODE_1,
+        offset: 0,
+        limit: 20,
+    });
+    let Some((ServerMessage::History { entries, .. }, _)) = connections[0].recieved.last() else {
+        panic!("Expected history, got: {:?}", connections[0].recieved);
+    };
+    let paid: i64 = entries
+        .iter()
+        .map(|e| e.summary())
+        .filter(|(action, ..)| *action == LedgerAction::WagerPayout { wager_id: 0 })
+        .map(|(.., amount)| amount)
+        .sum();
+    assert_eq!(paid, *SCORE_AMT_1 * 3);
 }
 #[rstest]
-fn wager_creation_invalid(
+fn wager_resolution_invalid(
     multi_client_state: StateFixture,
-    wager_creation_invalid_expected: MessageTestErrorExpected,
+    wager_resolution_invalid_expected: MessageTestErrorExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    connections[0].send_message(ClientMessage::CreateWager {
+    let msg = ClientMessage::ResolveWager {
         room_id: *ROOM_CODE_1,
-        name: WAGER_1.name(),
-        outcomes: WAGER_1.outcomes(),
-    });
-    connections[0].assert_failure(wager_creation_invalid_expected)
-}
-#[rstest]
-fn wager_joining(multi_client_state: StateFixture, wager_joining_expected: MessageTestExpected) {
-    let (_, mut connections) = multi_client_state;
+        wager_id: 0,
+        outcome_id: 2,
+    };
+    connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
+    connections[0].send_message(msg.clone());
     room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_2);
     room_setup_wager(&mut connections[0]);
-    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    connections[0].send_message(ClientMessage::JoinWager {
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 0);
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(wager_resolution_invalid_expected);
+    //Leaving takes the member's bets out of the wager, so it still resolves between whoever's left
+    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+        room_code: *ROOM_CODE_1,
+    });
+    connections[0].send_message(ClientMessage::ResolveWager {
         room_id: *ROOM_CODE_1,
         wager_id: 0,
         outcome_id: 0,
-        amount: *SCORE_AMT_1,
     });
-    connections[0].assert_success(wager_joining_expected)
+    assert_eq!(connections[0].errors.len(), 3);
+    assert!(connections[0].recieved.contains(&(
+        ServerMessage::WagerResolved {
+            room_code: *ROOM_CODE_1,
+            id: 0
+        },
+        Destination::PeersInclusive,
+    )));
 }
 
 #[rstest]
-fn wager_joining_invalid(
-    multi_client_state: StateFixture,
-    wager_joining_invalid_expected: MessageTestErrorExpected,
-) {
+fn history_request(multi_client_state: StateFixture) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::JoinWager {
-        room_id: *ROOM_CODE_1,
-        wager_id: 0,
-        outcome_id: 0,
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    connections[0].send_message_setup(ClientMessage::GiveScore {
+        room_code: *ROOM_CODE_1,
+        to: 1,
         amount: *SCORE_AMT_1,
+    });
+    connections[1].send_message(ClientMessage::RequestHistory {
+        room_code: *ROOM_CODE_1,
+        offset: 0,
+        limit: 2,
+    });
+    assert!(connections[1].errors.is_empty());
+    let [
+        (
+            ServerMessage::History {
+                entries,
+                offset,
+                total,
+                ..
+            },
+            Destination::Myself,
+        ),
+    ] = connections[1].recieved.as_slice()
+    else {
+        panic!("Expected history, got: {:?}", connections[1].recieved);
+    };
+    assert_eq!((*offset, *total), (0, 3));
+    assert_eq!(
+        entries.iter().map(|e| e.summary()).collect_vec(),
+        vec![
+            (LedgerAction::Give, Some(0), 1, *SCORE_AMT_1),
+            (LedgerAction::Give, Some(0), 0, -*SCORE_AMT_1),
+        ]
+    );
+}
+#[rstest]
+fn history_request_invalid(multi_client_state: StateFixture) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::RequestHistory {
+        room_code: *ROOM_CODE_1,
+        offset: 0,
+        limit: 10,
     };
     connections[0].send_message(msg.clone());
     room_init(&mut connections[0]);
     connections[0].send_message(msg.clone());
+    connections[0].assert_failure(vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::AddressNotInRoom(*USER_ADDR_1, *ROOM_CODE_1).into(),
+    ]);
+}
+
+#[fixture]
+fn undo_blessing_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ActionsUndone {
+                room_code: *ROOM_CODE_1,
+                count: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn undo_wager_resolution_expected() -> MessageTestExpected {
+    let mut wager = WAGER_1.clone();
+    wager.join(0, 0, *SCORE_AMT_1).unwrap();
+    wager.join(1, 1, *SCORE_AMT_1).unwrap();
+    vec![
+        (
+            ServerMessage::WagerCreated {
+                room_code: *ROOM_CODE_1,
+                wager,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ActionsUndone {
+                room_code: *ROOM_CODE_1,
+                count: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn undo_after_departure_expected() -> MessageTestExpected {
+    vec![
+        (
+            ServerMessage::WagerCreated {
+                room_code: *ROOM_CODE_1,
+                wager: WAGER_1.clone(),
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ActionsUndone {
+                room_code: *ROOM_CODE_1,
+                count: 2,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::WagerRemoved {
+                room_code: *ROOM_CODE_1,
+                wager_id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ActionsUndone {
+                room_code: *ROOM_CODE_1,
+                count: 1,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::PotResolved {
+                room_code: *ROOM_CODE_1,
+                id: 0,
+            },
+            Destination::PeersInclusive,
+        ),
+        (
+            ServerMessage::ScoreChanged {
+                room_code: *ROOM_CODE_1,
+                user_id: 0,
+                new_amount: *SCORE_AMT_1 * 2,
+            },
+            Destination::PeersInclusive,
+        ),
+    ]
+}
+#[fixture]
+fn undo_invalid_expected() -> MessageTestErrorExpected {
+    vec![
+        MessageHandleError::NonexistentRoom(*ROOM_CODE_1),
+        RoomMutationError::NothingToUndo(*ROOM_CODE_1).into(),
+        RoomMutationError::NothingToUndo(*ROOM_CODE_1).into(),
+    ]
+}
+#[rstest]
+fn undo_blessing(multi_client_state: StateFixture, undo_blessing_expected: MessageTestExpected) {
+    let (_, mut connections) = multi_client_state;
+    room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    room_setup_wager(&mut connections[0]);
-    connections[0].send_message(msg.clone());
-    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
-    connections[0].send_message(msg.clone());
-    connections[0].assert_failure(wager_joining_invalid_expected);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    connections[0].send_message(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    });
+    connections[0].assert_success(undo_blessing_expected);
 }
 #[rstest]
-fn wager_resolution(
+fn undo_wager_resolution(
     multi_client_state: StateFixture,
-    wager_resolution_expected: MessageTestExpected,
+    undo_wager_resolution_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-
     room_init(&mut connections[0]);
     room_join_setup_admin(&mut connections[0]);
     room_join_setup(&mut connections[1]);
@@ -864,70 +3661,178 @@ fn wager_resolution(
     room_setup_wager(&mut connections[0]);
     room_join_wager(&mut connections[0], 0);
     room_join_wager(&mut connections[1], 1);
-    connections[0].send_message(ClientMessage::ResolveWager {
+    connections[0].send_message_setup(ClientMessage::ResolveWager {
         room_id: *ROOM_CODE_1,
         wager_id: 0,
         outcome_id: 0,
     });
-    connections[0].assert_success(wager_resolution_expected);
+    connections[0].send_message(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    });
+    connections[0].assert_success(undo_wager_resolution_expected);
 }
 #[rstest]
-fn wager_resolution_invalid(
+fn undo_after_departure(
     multi_client_state: StateFixture,
-    wager_resolution_invalid_expected: MessageTestErrorExpected,
+    undo_after_departure_expected: MessageTestExpected,
 ) {
     let (_, mut connections) = multi_client_state;
-    let msg = ClientMessage::ResolveWager {
+    room_setup_stakes(&mut connections);
+    connections[0].send_message_setup(ClientMessage::UpdateRoomConfig {
+        room_code: *ROOM_CODE_1,
+        changes: RoomConfigPatch {
+            stake_policy: Some(StakePolicy::Void),
+            ..Default::default()
+        },
+    });
+    connections[0].send_message_setup(ClientMessage::RemoveFromRoom {
+        code: *ROOM_CODE_1,
+        id: 1,
+    });
+    //The pot was last checkpointed with member 1's stake in it, which they've since been refunded
+    connections[0].send_message(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 2,
+    });
+    connections[0].send_message(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    });
+    connections[0].send_message(ClientMessage::ResolvePot {
         room_id: *ROOM_CODE_1,
-        wager_id: 0,
-        outcome_id: 2,
-    };
-    connections[0].send_message(msg.clone());
+        pot_id: 0,
+        winners: vec![0],
+        split: PotSplit::Equal,
+        allow_outsiders: false,
+    });
+    connections[0].assert_success(undo_after_departure_expected);
+}
+#[rstest]
+fn undo_keeps_locks(multi_client_state: StateFixture) {
+    let (_, mut connections) = multi_client_state;
     room_init(&mut connections[0]);
-    connections[0].send_message(msg.clone());
     room_join_setup_admin(&mut connections[0]);
-    room_join_setup(&mut connections[1]);
-    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_2);
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_2);
+    room_setup_pot(&mut connections[0]);
     room_setup_wager(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
     room_join_wager(&mut connections[0], 0);
-    room_join_wager(&mut connections[1], 0);
-    connections[0].send_message(msg.clone());
-    connections[1].send_message_setup(ClientMessage::LeaveRoom {
+    connections[0].send_message_setup(ClientMessage::LockPot {
         room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        closes_at: None,
     });
-    connections[0].send_message(ClientMessage::ResolveWager {
+    connections[0].send_message_setup(ClientMessage::LockWager {
+        room_code: *ROOM_CODE_1,
+        wager_id: 0,
+        closes_at: None,
+    });
+    connections[0].send_message_setup(ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 2,
+    });
+    //The stakes came back out, but betting stays closed
+    connections[0].send_message(ClientMessage::JoinPot {
+        room_code: *ROOM_CODE_1,
+        pot_id: 0,
+        amount: None,
+    });
+    connections[0].send_message(ClientMessage::JoinWager {
         room_id: *ROOM_CODE_1,
         wager_id: 0,
         outcome_id: 0,
+        amount: *SCORE_AMT_1,
     });
-    connections[0].assert_failure(wager_resolution_invalid_expected);
+    connections[0].assert_failure(vec![
+        PotMutationError::PotLocked(0).into(),
+        WagerMutationError::WagerLocked(0).into(),
+    ]);
+}
+#[rstest]
+fn undo_invalid(multi_client_state: StateFixture, undo_invalid_expected: MessageTestErrorExpected) {
+    let (_, mut connections) = multi_client_state;
+    let msg = ClientMessage::Undo {
+        room_code: *ROOM_CODE_1,
+        count: 1,
+    };
+    connections[0].send_message(msg.clone());
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    connections[0].send_message(msg.clone());
+    room_setup_score(&mut connections[0], [0], *SCORE_AMT_1);
+    connections[0].send_message_setup(msg.clone());
+    connections[0].send_message(msg.clone());
+    connections[0].assert_failure(undo_invalid_expected);
 }
 
 fn room_init(con: &mut MockConnection) {
     con.send_message_setup(ClientMessage::CreateRoom {
         code: *ROOM_CODE_1,
         admin_pass: "pass".to_owned(),
+        config: RoomConfig::default(),
     });
 }
 
-fn room_join_setup(con: &mut MockConnection) {
-    con.send_message_setup(ClientMessage::JoinRoom {
+fn room_init_with_credit(con: &mut MockConnection, credit_limit: i64) {
+    con.send_message_setup(ClientMessage::CreateRoom {
         code: *ROOM_CODE_1,
-        name: USER_NAME_1.clone(),
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig {
+            credit_limit,
+            ..Default::default()
+        },
     });
 }
 
-fn room_join_setup_admin(con: &mut MockConnection) {
-    room_join_setup(con);
+fn room_join_setup(con: &mut MockConnection) -> String {
+    let responses = con
+        .state
+        .read()
+        .handle_message(
+            ClientMessage::JoinRoom {
+                code: *ROOM_CODE_1,
+                name: USER_NAME_1.clone(),
+            },
+            con.addr,
+        )
+        .expect("Joining should succeed during test setup");
+    responses
+        .into_iter()
+        .find_map(|(msg, _)| match msg {
+            ServerMessage::SynchronizeRoom { member_token, .. } => member_token,
+            _ => None,
+        })
+        .expect("Joining should send a room sync")
+}
+
+fn room_join_setup_admin(con: &mut MockConnection) -> String {
+    let token = room_join_setup(con);
     con.send_message_setup(ClientMessage::RequestAdmin {
         room: *ROOM_CODE_1,
         password: "pass".to_owned(),
     });
+    token
+}
+
+//Both members end up with a stake in pot 0 and on opposite sides of wager 0
+fn room_setup_stakes(connections: &mut [MockConnection]) {
+    room_init(&mut connections[0]);
+    room_join_setup_admin(&mut connections[0]);
+    room_join_setup(&mut connections[1]);
+    room_setup_score(&mut connections[0], [0, 1], *SCORE_AMT_1 * 2);
+    room_setup_pot(&mut connections[0]);
+    room_join_pot(&mut connections[0]);
+    room_join_pot(&mut connections[1]);
+    room_setup_wager(&mut connections[0]);
+    room_join_wager(&mut connections[0], 0);
+    room_join_wager(&mut connections[1], 1);
 }
 
 fn room_setup_score(con: &mut MockConnection, ids: impl IntoIterator<Item = ID>, amt: i64) {
     for id in ids {
         con.send_message_setup(ClientMessage::BlessScore {
+            room_code: *ROOM_CODE_1,
             to: id,
             amount: amt,
         });
@@ -939,13 +3844,25 @@ fn room_setup_pot(con: &mut MockConnection) {
         room_code: *ROOM_CODE_1,
         score_requirement: *SCORE_AMT_1,
         description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
     });
 }
 
+fn room_setup_variable_pot(con: &mut MockConnection) {
+    con.send_message_setup(ClientMessage::CreatePot {
+        room_code: *ROOM_CODE_1,
+        score_requirement: *SCORE_AMT_1,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: true,
+    });
+}
 fn room_join_pot(con: &mut MockConnection) {
     con.send_message_setup(ClientMessage::JoinPot {
         room_code: *ROOM_CODE_1,
         pot_id: 0,
+        amount: None,
     });
 }
 
@@ -954,6 +3871,18 @@ fn room_setup_wager(con: &mut MockConnection) {
         room_id: *ROOM_CODE_1,
         name: WAGER_1.name(),
         outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::FixedOdds,
+        closes_at: None,
+    });
+}
+
+fn room_setup_dynamic_wager(con: &mut MockConnection) {
+    con.send_message_setup(ClientMessage::CreateWager {
+        room_id: *ROOM_CODE_1,
+        name: WAGER_1.name(),
+        outcomes: WAGER_1.outcomes(),
+        wager_kind: WagerKind::DynamicOdds,
+        closes_at: None,
     });
 }
 
@@ -15,6 +15,7 @@ use crate::{
 
 mod message_handling;
 mod state_manipulation;
+mod storage;
 
 #[allow(dead_code)]
 struct MockConnection {
@@ -40,9 +41,7 @@ impl MockConnection {
         self.state
             .read()
             .handle_message(msg.clone(), self.addr)
-            .expect(&format!(
-                "Message sending failed during test setup: {msg:?}"
-            ));
+            .unwrap_or_else(|_| panic!("Message sending failed during test setup: {msg:?}"));
     }
     fn send_message(&mut self, msg: ClientMessage) {
         match self.state.read().handle_message(msg, self.addr) {
@@ -50,6 +49,22 @@ impl MockConnection {
             Err(err) => self.errors.push(err),
         }
     }
+    //Tokens are random, so blank them out of recieved messages to keep expectations static
+    fn take_member_token(&mut self) -> String {
+        self.recieved
+            .iter_mut()
+            .find_map(|(msg, _)| match msg {
+                ServerMessage::SynchronizeRoom { member_token, .. } => member_token.take(),
+                _ => None,
+            })
+            .expect("Should have recieved a room sync with a token")
+    }
+    //Messages pushed outside of a reply, eg. by the background tasks
+    fn take_pushed(&mut self) -> Vec<serde_json::Value> {
+        std::iter::from_fn(|| self.rx.try_next().ok().flatten())
+            .map(|msg| serde_json::from_str(msg.to_text().unwrap()).unwrap())
+            .collect()
+    }
 }
 
 type StateFixture = (Arc<RwLock<ServerState>>, Vec<MockConnection>);
@@ -0,0 +1,96 @@
+/*
+* Do rooms survive being written out and read back in?
+*/
+
+use std::path::PathBuf;
+
+use super::*;
+use crate::state::{
+    room::config::RoomConfig,
+    storage::{JsonFileStorage, RoomStorage},
+};
+
+fn snapshot_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("score_tracker_{name}_{}.json", std::process::id()))
+}
+
+#[test]
+fn missing_snapshot_loads_empty() {
+    let storage = JsonFileStorage::new(snapshot_path("missing"));
+    assert!(storage.load().unwrap().is_empty());
+}
+
+#[test]
+fn rooms_survive_restart() {
+    let path = snapshot_path("restart");
+    let code = RoomCode::from("AAAAAAAA");
+    let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+
+    let state = ServerState::with_storage(JsonFileStorage::new(&path)).unwrap();
+    let mut con = MockConnection::new(state.clone(), addr);
+    con.send_message_setup(ClientMessage::CreateRoom {
+        code,
+        admin_pass: "pass".to_owned(),
+        config: RoomConfig {
+            max_members: Some(4),
+            ..Default::default()
+        },
+    });
+    con.send_message_setup(ClientMessage::JoinRoom {
+        code,
+        name: "user1".to_owned(),
+    });
+    con.send_message_setup(ClientMessage::RequestAdmin {
+        room: code,
+        password: "pass".to_owned(),
+    });
+    con.send_message_setup(ClientMessage::BlessScore {
+        room_code: code,
+        to: 0,
+        amount: 500,
+    });
+    con.send_message_setup(ClientMessage::CreatePot {
+        room_code: code,
+        score_requirement: 100,
+        description: "description".to_owned(),
+        closes_at: None,
+        variable_buy_in: false,
+    });
+    con.send_message_setup(ClientMessage::JoinPot {
+        room_code: code,
+        pot_id: 0,
+        amount: None,
+    });
+    state.read().save_rooms().unwrap();
+
+    let restored = ServerState::with_storage(JsonFileStorage::new(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut con = MockConnection::new(restored.clone(), addr);
+    con.send_message(ClientMessage::JoinRoom {
+        code,
+        name: "user2".to_owned(),
+    });
+    con.send_message(ClientMessage::RequestAdmin {
+        room: code,
+        password: "pass".to_owned(),
+    });
+    assert!(con.errors.is_empty(), "{:?}", con.errors);
+    let Some((
+        ServerMessage::SynchronizeRoom {
+            members,
+            pots,
+            config,
+            ..
+        },
+        _,
+    )) = con.recieved.get(1)
+    else {
+        panic!("Expected room sync, got: {:?}", con.recieved);
+    };
+    let restored_member = members.iter().find(|m| m.id() == 0).unwrap();
+    assert_eq!(restored_member.score(), 400);
+    assert_eq!(pots[0].resolve(), 100);
+    assert_eq!(pots[0].participants().collect_vec(), vec![&0]);
+    assert!(members.iter().any(|m| m.id() == 1));
+    assert_eq!(config.max_members, Some(4));
+}
@@ -12,10 +12,10 @@ use schemars::{Schema, schema_for};
 
 pub fn gen_schema() -> Result<(), Box<dyn Error>> {
     let out_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../type_schemas");
-    if let Err(e) = fs::DirBuilder::new().create(&out_dir) {
-        if e.kind() != std::io::ErrorKind::AlreadyExists {
-            return Err(e.into());
-        };
+    if let Err(e) = fs::DirBuilder::new().create(&out_dir)
+        && e.kind() != std::io::ErrorKind::AlreadyExists
+    {
+        return Err(e.into());
     };
     let schemas = [
         (schema_for!(ServerMessage), "server_message"),